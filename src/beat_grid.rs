@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+use bevy::utils::{Duration, Instant};
+
+/// The ideal beat times of a session.
+///
+/// Beat `origin_beat` is at `origin` and every other beat is a whole number of
+/// periods away, so the grid never accumulates frame timing errors.
+#[derive(Resource)]
+pub struct BeatGrid {
+    origin: Instant,
+    origin_beat: i64,
+    period: Duration,
+}
+
+/// `instant + secs`, where `secs` may be negative.
+pub fn offset(instant: Instant, secs: f64) -> Instant {
+    if secs >= 0.0 {
+        instant + Duration::from_secs_f64(secs)
+    } else {
+        instant - Duration::from_secs_f64(-secs)
+    }
+}
+
+/// `a - b` in seconds, may be negative.
+pub fn signed_secs(a: Instant, b: Instant) -> f64 {
+    if a >= b {
+        (a - b).as_secs_f64()
+    } else {
+        -(b - a).as_secs_f64()
+    }
+}
+
+impl BeatGrid {
+    pub fn new(start: Instant, period: Duration) -> Self {
+        Self {
+            origin: start,
+            origin_beat: 0,
+            period,
+        }
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Position of `instant` in beats, e.g. 3.25 is a quarter beat after beat 3.
+    pub fn position(&self, instant: Instant) -> f64 {
+        self.origin_beat as f64 + signed_secs(instant, self.origin) / self.period.as_secs_f64()
+    }
+
+    pub fn beat_time(&self, beat: i64) -> Instant {
+        offset(
+            self.origin,
+            (beat - self.origin_beat) as f64 * self.period.as_secs_f64(),
+        )
+    }
+
+    /// The beat at or before `instant`.
+    pub fn beat_before(&self, instant: Instant) -> i64 {
+        self.position(instant).floor() as i64
+    }
+
+    /// The first beat at or after `instant`.
+    pub fn beat_at_or_after(&self, instant: Instant) -> i64 {
+        self.position(instant).ceil() as i64
+    }
+
+    /// Shifts the grid so that a beat falls on `instant`.
+    pub fn align(&mut self, instant: Instant) {
+        self.origin_beat = self.position(instant).round() as i64;
+        self.origin = instant;
+    }
+
+    /// Changes the tempo while keeping the phase of the beat in progress at `now`.
+    pub fn set_period(&mut self, period: Duration, now: Instant) {
+        let position = self.position(now);
+        let beat = position.floor();
+
+        self.origin = offset(now, -(position - beat) * period.as_secs_f64());
+        self.origin_beat = beat as i64;
+        self.period = period;
+    }
+}
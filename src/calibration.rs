@@ -0,0 +1,82 @@
+use bevy::prelude::*;
+use bevy::utils::Instant;
+
+use crate::stats::median;
+
+/// Number of taps collected by a calibration run.
+pub const CALIBRATION_TAPS: usize = 16;
+
+/// Systematic offsets of the player's setup in seconds, positive when taps land late.
+#[derive(Resource, Default)]
+pub struct LatencyOffset {
+    /// Audio output plus input latency, subtracted from every tap.
+    pub audio: f64,
+    /// Display plus input latency, measured by tapping along the clock marker.
+    pub visual: Option<f64>,
+}
+
+impl LatencyOffset {
+    /// How far ahead of the beat grid the clock marker is drawn, so that it is
+    /// seen reaching a beat when that beat's click is heard.
+    pub fn marker_shift(&self) -> f64 {
+        self.visual.map_or(0.0, |visual| visual - self.audio)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CalibrationKind {
+    /// Tap along the clicks.
+    Audio,
+    /// Tap when the marker hits 12 o'clock, with the clicks muted.
+    Visual,
+}
+
+impl CalibrationKind {
+    pub fn label(&self) -> &str {
+        match self {
+            CalibrationKind::Audio => "audio",
+            CalibrationKind::Visual => "visual",
+        }
+    }
+}
+
+/// Collects raw taps against the beat grid to measure a [`LatencyOffset`].
+#[derive(Resource, Default)]
+pub struct Calibration {
+    run: Option<(CalibrationKind, Instant, Vec<f64>)>,
+}
+
+impl Calibration {
+    pub fn start(&mut self, kind: CalibrationKind) {
+        self.run = Some((kind, Instant::now(), Vec::with_capacity(CALIBRATION_TAPS)));
+    }
+
+    pub fn kind(&self) -> Option<CalibrationKind> {
+        self.run.as_ref().map(|(kind, _, _)| *kind)
+    }
+
+    /// Number of taps collected so far, if a run is active.
+    pub fn progress(&self) -> Option<usize> {
+        self.run.as_ref().map(|(_, _, samples)| samples.len())
+    }
+
+    /// Records the raw error of a tap against its nearest beat.
+    /// Returns the measured offset once enough taps are collected.
+    pub fn record(&mut self, press: Instant, delta: f64) -> Option<(CalibrationKind, f64)> {
+        let (kind, started, samples) = self.run.as_mut()?;
+        // Ignore the press that started the run
+        if press < *started {
+            return None;
+        }
+
+        samples.push(delta);
+        if samples.len() < CALIBRATION_TAPS {
+            return None;
+        }
+
+        // Never empty here, at least one tap was just pushed
+        let result = median(samples).map(|offset| (*kind, offset));
+        self.run = None;
+        result
+    }
+}
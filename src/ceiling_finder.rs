@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+use bevy::utils::Instant;
+
+use crate::{
+    Bpm, Division, Polyrhythm, Tapped, TimeSignature, beat_grid::BeatGrid,
+    speed_trainer::StepAccuracy,
+};
+
+/// Ramps the tempo from `start_bpm` until the player loses control,
+/// and reports the fastest tempo they kept up with.
+#[derive(Resource)]
+pub struct CeilingFinder {
+    pub start_bpm: f32,
+    /// BPM added after every passed step.
+    pub step: f32,
+    pub bars_per_step: u32,
+    /// Largest mean |delta| in seconds that still counts as in control.
+    pub max_mean_delta: f64,
+    /// Largest fraction of expected taps that may be missing.
+    pub max_miss_rate: f64,
+    running: bool,
+    best: Option<f32>,
+    accuracy: StepAccuracy,
+    /// The ceiling found by the last finished run.
+    pub result: Option<Option<f32>>,
+}
+
+impl Default for CeilingFinder {
+    fn default() -> Self {
+        Self {
+            start_bpm: 120.0,
+            step: 5.0,
+            bars_per_step: 2,
+            max_mean_delta: 2.0 / 60.0,
+            max_miss_rate: 0.25,
+            running: false,
+            best: None,
+            accuracy: StepAccuracy::default(),
+            result: None,
+        }
+    }
+}
+
+impl CeilingFinder {
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn toggle(&mut self, bpm: &mut Bpm) {
+        self.running = !self.running;
+        self.best = None;
+        self.accuracy.reset();
+        if self.running {
+            bpm.0 = self.start_bpm;
+        }
+    }
+
+    pub fn label(&self, bpm: &Bpm) -> String {
+        if self.running {
+            return format!("testing {} BPM", bpm.label());
+        }
+
+        match self.result {
+            Some(Some(ceiling)) => format!("{} BPM", ceiling.round()),
+            Some(None) => format!("below {} BPM", self.start_bpm),
+            None => "-".to_string(),
+        }
+    }
+}
+
+pub fn ceiling_finder(
+    mut ceiling_finder: ResMut<CeilingFinder>,
+    mut tapped: EventReader<Tapped>,
+    mut bpm: ResMut<Bpm>,
+    beat_grid: Res<BeatGrid>,
+    time_signature: Res<TimeSignature>,
+    division: Res<Division>,
+    polyrhythm: Res<Polyrhythm>,
+) {
+    if !ceiling_finder.running {
+        tapped.clear();
+        return;
+    }
+
+    let beat = beat_grid.beat_before(Instant::now());
+    let finder = ceiling_finder.as_mut();
+    let beats = finder.accuracy.update(beat, &mut tapped);
+
+    if beats < (finder.bars_per_step * time_signature.beats_per_bar()) as i64 {
+        return;
+    }
+
+    // Taps expected per bar, both hands tap in a polyrhythm
+    let per_bar: usize = match polyrhythm.divisions() {
+        Some(divisions) => divisions
+            .iter()
+            .map(|division| time_signature.onsets(*division).len())
+            .sum(),
+        None => time_signature.onsets(division.0).len(),
+    };
+    let expected = beats as f64 / time_signature.beats_per_bar() as f64 * per_bar as f64;
+    let miss_rate = (1.0 - finder.accuracy.taps as f64 / expected).max(0.0);
+
+    if finder.accuracy.mean() <= finder.max_mean_delta && miss_rate <= finder.max_miss_rate {
+        finder.best = Some(bpm.0);
+        bpm.step(finder.step);
+        finder.accuracy.reset();
+    } else {
+        finder.result = Some(finder.best);
+        finder.running = false;
+        if let Some(best) = finder.best {
+            bpm.0 = best;
+        }
+    }
+}
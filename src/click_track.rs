@@ -0,0 +1,271 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use bevy::audio::{CpalSample, Decodable, Source};
+use bevy::prelude::*;
+use bevy::utils::{Duration, Instant};
+
+/// Sample rate of the click track. rodio resamples it to the device rate.
+pub const SAMPLE_RATE: u32 = 48_000;
+
+/// Number of frames mixed per lock of the shared state.
+const BLOCK_FRAMES: usize = 64;
+
+/// How often the frame -> wall clock mapping is re-estimated.
+const EPOCH_WINDOW: Duration = Duration::from_secs(1);
+
+/// A click queued at an exact frame of the click track.
+#[derive(Clone, Copy)]
+pub struct ScheduledClick {
+    pub frame: u64,
+    pub sound: usize,
+    pub volume: f32,
+}
+
+struct Voice {
+    samples: Arc<[f32]>,
+    position: usize,
+    volume: f32,
+}
+
+struct Shared {
+    // Mono samples at `SAMPLE_RATE`, indexed like `AudioHandles::handles`
+    sounds: Vec<Option<Arc<[f32]>>>,
+    // Sorted by frame
+    queue: VecDeque<ScheduledClick>,
+    voices: Vec<Voice>,
+    // Frames handed to the audio backend so far
+    rendered: u64,
+    // Estimated wall clock time of frame 0
+    epoch: Instant,
+    window_min: Option<Instant>,
+    window_opened: Option<Instant>,
+}
+
+/// An endless audio stream that mixes clicks at sample-accurate positions.
+///
+/// The ECS side queues clicks ahead of time with [`ClickTrack::schedule`], and the
+/// audio thread starts each one exactly at its frame, independent of the frame rate.
+/// Frames are mapped to [`Instant`]s by observing when the audio thread pulls them.
+#[derive(Asset, Resource, TypePath, Clone)]
+pub struct ClickTrack {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Default for ClickTrack {
+    fn default() -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(Shared {
+                sounds: Vec::new(),
+                queue: VecDeque::new(),
+                voices: Vec::new(),
+                rendered: 0,
+                epoch: Instant::now(),
+                window_min: None,
+                window_opened: None,
+            })),
+        }
+    }
+}
+
+pub fn frames_to_duration(frames: u64) -> Duration {
+    Duration::from_secs_f64(frames as f64 / SAMPLE_RATE as f64)
+}
+
+pub fn duration_to_frames(duration: Duration) -> u64 {
+    (duration.as_secs_f64() * SAMPLE_RATE as f64).round() as u64
+}
+
+impl ClickTrack {
+    fn lock(&self) -> MutexGuard<'_, Shared> {
+        self.shared.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Decodes `source` and makes it available as sound `index`.
+    pub fn set_sound(&self, index: usize, source: &AudioSource) {
+        let samples = decode_mono(source);
+        let mut shared = self.lock();
+        if shared.sounds.len() <= index {
+            shared.sounds.resize(index + 1, None);
+        }
+        shared.sounds[index] = Some(samples);
+    }
+
+    /// Queues a click. Clicks for frames already rendered play as soon as possible.
+    pub fn schedule(&self, click: ScheduledClick) {
+        let mut shared = self.lock();
+        let at = shared
+            .queue
+            .iter()
+            .rposition(|c| c.frame <= click.frame)
+            .map_or(0, |i| i + 1);
+        shared.queue.insert(at, click);
+    }
+
+    /// Drops every queued click that hasn't started playing yet.
+    pub fn cancel_pending(&self) {
+        self.lock().queue.clear();
+    }
+
+    /// Whether the audio backend has pulled any frames. Until then frames can't be
+    /// mapped to wall clock time, the first render resets the estimate.
+    pub fn is_playing(&self) -> bool {
+        self.lock().rendered > 0
+    }
+
+    /// First frame that has not been handed to the audio backend yet.
+    pub fn rendered_frame(&self) -> u64 {
+        self.lock().rendered
+    }
+
+    /// Wall clock time at which `frame` is played.
+    pub fn frame_to_instant(&self, frame: u64) -> Instant {
+        self.lock().epoch + frames_to_duration(frame)
+    }
+
+    /// Frame played at `instant`.
+    pub fn instant_to_frame(&self, instant: Instant) -> u64 {
+        duration_to_frames(instant.saturating_duration_since(self.lock().epoch))
+    }
+}
+
+impl Shared {
+    fn update_epoch(&mut self) {
+        let now = Instant::now();
+        let Some(candidate) = now.checked_sub(frames_to_duration(self.rendered)) else {
+            return;
+        };
+
+        // The audio thread pulls frames in bursts, the earliest candidate in a window
+        // corresponds to a full output buffer and is the most stable estimate.
+        self.window_min = Some(
+            self.window_min
+                .map_or(candidate, |window_min| window_min.min(candidate)),
+        );
+
+        let opened = *self.window_opened.get_or_insert(now);
+        if self.rendered == 0 || now - opened >= EPOCH_WINDOW {
+            self.epoch = self.window_min.take().unwrap_or(candidate);
+            self.window_opened = Some(now);
+        }
+    }
+
+    fn render(&mut self, buffer: &mut [f32]) {
+        self.update_epoch();
+
+        for (i, out) in buffer.iter_mut().enumerate() {
+            let frame = self.rendered + i as u64;
+
+            while let Some(click) = self.queue.front().copied() {
+                if click.frame > frame {
+                    break;
+                }
+                self.queue.pop_front();
+                if let Some(Some(samples)) = self.sounds.get(click.sound) {
+                    self.voices.push(Voice {
+                        samples: samples.clone(),
+                        position: 0,
+                        volume: click.volume,
+                    });
+                }
+            }
+
+            let mut sample = 0.0;
+            for voice in &mut self.voices {
+                if let Some(s) = voice.samples.get(voice.position) {
+                    sample += s * voice.volume;
+                    voice.position += 1;
+                }
+            }
+            *out = sample.clamp(-1.0, 1.0);
+
+            self.voices.retain(|v| v.position < v.samples.len());
+        }
+
+        self.rendered += buffer.len() as u64;
+    }
+}
+
+fn decode_mono(source: &AudioSource) -> Arc<[f32]> {
+    let decoder = source.decoder();
+    let channels = decoder.channels().max(1) as usize;
+    let rate = decoder.sample_rate();
+    let interleaved: Vec<f32> = decoder.map(|s| s.to_sample::<f32>()).collect();
+
+    let mono: Vec<f32> = interleaved
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+
+    if rate == SAMPLE_RATE || mono.is_empty() {
+        return mono.into();
+    }
+
+    // Linear resampling is good enough for short percussive clicks
+    let len = (mono.len() as u64 * SAMPLE_RATE as u64 / rate as u64) as usize;
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * rate as f64 / SAMPLE_RATE as f64;
+            let index = pos as usize;
+            let t = (pos - index as f64) as f32;
+            let a = mono[index.min(mono.len() - 1)];
+            let b = mono[(index + 1).min(mono.len() - 1)];
+            a + (b - a) * t
+        })
+        .collect()
+}
+
+pub struct ClickTrackDecoder {
+    shared: Arc<Mutex<Shared>>,
+    buffer: [f32; BLOCK_FRAMES],
+    position: usize,
+}
+
+impl Iterator for ClickTrackDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position == BLOCK_FRAMES {
+            self.shared
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .render(&mut self.buffer);
+            self.position = 0;
+        }
+
+        let sample = self.buffer[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl Source for ClickTrackDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Decodable for ClickTrack {
+    type DecoderItem = f32;
+    type Decoder = ClickTrackDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        ClickTrackDecoder {
+            shared: self.shared.clone(),
+            buffer: [0.0; BLOCK_FRAMES],
+            position: BLOCK_FRAMES,
+        }
+    }
+}
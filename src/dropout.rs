@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+
+use crate::text_entry::{TextEntered, TextTarget};
+
+/// Probabilities cycled through by the dropout button.
+pub const PROBABILITIES: &[f64] = &[0.1, 0.25, 0.5, 0.75];
+
+/// Randomly leaves out metronome ticks, each with the same probability.
+///
+/// A tick is dropped when a hash of the seed and the beat falls under the
+/// probability, so the same seed drops the same beats of the grid.
+#[derive(Resource, Default)]
+pub struct ClickDropout {
+    probability: Option<usize>,
+    seed: u64,
+    // Whether the seed was entered, rather than picked when the mode started
+    fixed_seed: bool,
+}
+
+impl ClickDropout {
+    /// Whether the tick of `beat` is left out.
+    pub fn is_dropped(&self, beat: i64) -> bool {
+        let Some(i) = self.probability else {
+            return false;
+        };
+        let sample = (split_mix(self.seed ^ beat as u64) >> 11) as f64 / (1u64 << 53) as f64;
+        sample < PROBABILITIES[i]
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.probability.is_some()
+    }
+
+    /// Switches to the next of [`PROBABILITIES`], turning off after the last one.
+    pub fn next(&mut self) {
+        self.probability = match self.probability {
+            None => Some(0),
+            Some(i) if i + 1 < PROBABILITIES.len() => Some(i + 1),
+            Some(_) => None,
+        };
+        if self.probability == Some(0) && !self.fixed_seed {
+            self.seed = random_seed();
+        }
+    }
+
+    /// Uses `seed` from now on, or a random one each time the mode starts when `None`.
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.fixed_seed = seed.is_some();
+        self.seed = seed.unwrap_or_else(random_seed);
+    }
+
+    pub fn label(&self) -> String {
+        match self.probability {
+            Some(i) if self.fixed_seed => {
+                format!("{:.0}%, seed {}", PROBABILITIES[i] * 100.0, self.seed)
+            }
+            Some(i) => format!("{:.0}%", PROBABILITIES[i] * 100.0),
+            None => "off".to_string(),
+        }
+    }
+}
+
+// SplitMix64, spreads neighbouring beats over the whole range
+fn split_mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn random_seed() -> u64 {
+    #[cfg(not(target_arch = "wasm32"))]
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+
+    #[cfg(target_arch = "wasm32")]
+    let nanos = web_sys::window()
+        .and_then(|window| window.performance())
+        .map_or(0.0, |performance| {
+            (performance.time_origin() + performance.now()) * 1_000_000.0
+        }) as u64;
+
+    split_mix(nanos)
+}
+
+/// Applies a seed typed by the player, an empty or invalid one means random.
+pub fn dropout_seed(mut entered: EventReader<TextEntered>, mut dropout: ResMut<ClickDropout>) {
+    for TextEntered(target, text) in entered.read() {
+        if *target == TextTarget::DropoutSeed {
+            dropout.set_seed(text.trim().parse().ok());
+        }
+    }
+}
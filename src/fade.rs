@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+
+/// Lengths of the fade cycled through by the fade button, in bars.
+pub const FADE_BARS: &[u32] = &[4, 8, 16];
+
+/// Lowers the metronome volume bar by bar down to silence, and optionally back up.
+#[derive(Resource, Default)]
+pub struct ClickFade {
+    bars: Option<usize>,
+    /// Fade back up after reaching silence, then down again.
+    pub back_up: bool,
+    // Beat of the grid the fade started on
+    start_beat: i64,
+}
+
+impl ClickFade {
+    /// Volume factor of the metronome at `beat`, from 1 down to 0.
+    pub fn volume(&self, beat: i64, beats_per_bar: u32) -> f32 {
+        let Some(i) = self.bars else {
+            return 1.0;
+        };
+        let length = (FADE_BARS[i] * beats_per_bar) as f64;
+        let progress = ((beat - self.start_beat) as f64 / length).max(0.0);
+
+        let progress = if self.back_up {
+            // Down on even lengths, up on odd ones
+            let phase = progress.rem_euclid(2.0);
+            if phase <= 1.0 { phase } else { 2.0 - phase }
+        } else {
+            progress.min(1.0)
+        };
+        (1.0 - progress) as f32
+    }
+
+    /// Switches to the next of [`FADE_BARS`], turning off after the last one.
+    /// The fade restarts at `start_beat`.
+    pub fn next(&mut self, start_beat: i64) {
+        self.bars = match self.bars {
+            None => Some(0),
+            Some(i) if i + 1 < FADE_BARS.len() => Some(i + 1),
+            Some(_) => None,
+        };
+        self.start_beat = start_beat;
+    }
+
+    pub fn label(&self, beat: i64, beats_per_bar: u32) -> String {
+        let Some(i) = self.bars else {
+            return "off".to_string();
+        };
+        let back_up = if self.back_up { " and back" } else { "" };
+        format!(
+            "{} bars{}, {:.0}%",
+            FADE_BARS[i],
+            back_up,
+            self.volume(beat, beats_per_bar) * 100.0
+        )
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::utils::{Duration, Instant};
+
+use crate::stats::mean;
+
+/// Intervals the analysis is made from.
+const MAX_INTERVALS: usize = 32;
+/// A longer pause starts a new run.
+const RESET_GAP: Duration = Duration::from_secs(3);
+
+/// Taps without a metronome, judged against the player's own tempo instead of a grid.
+#[derive(Resource, Default)]
+pub struct FreeRunning {
+    pub enabled: bool,
+    presses: VecDeque<Instant>,
+}
+
+impl FreeRunning {
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.presses.clear();
+    }
+
+    /// Adds a press, returns how much longer than the mean interval before it
+    /// the latest interval was, in seconds.
+    pub fn press(&mut self, press: Instant) -> Option<f64> {
+        if self
+            .presses
+            .back()
+            .is_some_and(|last| press.saturating_duration_since(*last) > RESET_GAP)
+        {
+            self.presses.clear();
+        }
+        self.presses.push_back(press);
+        if self.presses.len() > MAX_INTERVALS + 1 {
+            self.presses.pop_front();
+        }
+
+        let intervals = self.intervals();
+        let (latest, previous) = intervals.split_last()?;
+        if previous.is_empty() {
+            return None;
+        }
+        Some(latest - mean(previous))
+    }
+
+    // Seconds between consecutive presses
+    fn intervals(&self) -> Vec<f64> {
+        self.presses
+            .iter()
+            .zip(self.presses.iter().skip(1))
+            .map(|(a, b)| (*b - *a).as_secs_f64())
+            .collect()
+    }
+
+    /// Estimated tempo from the mean interval.
+    pub fn bpm(&self) -> Option<f64> {
+        let intervals = self.intervals();
+        (!intervals.is_empty()).then(|| 60.0 / mean(&intervals))
+    }
+
+    /// Variance of the intervals, in seconds squared.
+    pub fn variance(&self) -> Option<f64> {
+        let intervals = self.intervals();
+        if intervals.len() < 2 {
+            return None;
+        }
+        let mean = mean(&intervals);
+        Some(intervals.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / intervals.len() as f64)
+    }
+
+    /// Change of the tempo over the run in BPM per minute, the least squares
+    /// slope of the tempo of each interval against the time it ended.
+    pub fn drift(&self) -> Option<f64> {
+        let start = *self.presses.front()?;
+        let (times, bpms): (Vec<f64>, Vec<f64>) = self
+            .presses
+            .iter()
+            .skip(1)
+            .zip(self.intervals())
+            .map(|(end, interval)| ((*end - start).as_secs_f64(), 60.0 / interval))
+            .unzip();
+        if times.len() < 3 {
+            return None;
+        }
+
+        let (mean_t, mean_bpm) = (mean(&times), mean(&bpms));
+        let covariance = times
+            .iter()
+            .zip(&bpms)
+            .map(|(t, bpm)| (t - mean_t) * (bpm - mean_bpm))
+            .sum::<f64>();
+        let variance = times.iter().map(|t| (t - mean_t).powi(2)).sum::<f64>();
+        (variance > 0.0).then(|| covariance / variance * 60.0)
+    }
+
+    pub fn label(&self) -> String {
+        match (self.enabled, self.bpm()) {
+            (false, _) => "off".to_string(),
+            (true, Some(bpm)) => format!("{:.1} BPM", bpm),
+            (true, None) => "tap at your own tempo".to_string(),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use bevy::prelude::*;
+
+/// Bars cycled through by the gap click button, as (audible, silent).
+pub const GAPS: &[(u32, u32)] = &[(2, 2), (4, 4), (4, 2), (1, 1)];
+
+/// Plays the metronome for some bars, then drops it out for some bars while
+/// the player keeps tapping.
+#[derive(Resource, Default)]
+pub struct GapClick {
+    gap: Option<usize>,
+}
+
+impl GapClick {
+    /// Whether the metronome is dropped out during `bar`.
+    pub fn is_silent(&self, bar: i64) -> bool {
+        self.gap.is_some_and(|i| {
+            let (audible, silent) = GAPS[i];
+            bar.rem_euclid((audible + silent) as i64) >= audible as i64
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.gap.is_some()
+    }
+
+    /// Switches to the next of [`GAPS`], turning off after the last one.
+    pub fn next(&mut self) {
+        self.gap = match self.gap {
+            None => Some(0),
+            Some(i) if i + 1 < GAPS.len() => Some(i + 1),
+            Some(_) => None,
+        };
+    }
+
+    pub fn label(&self) -> String {
+        match self.gap {
+            Some(i) => format!("{} on / {} off", GAPS[i].0, GAPS[i].1),
+            None => "off".to_string(),
+        }
+    }
+}
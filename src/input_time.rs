@@ -0,0 +1,428 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::{Duration, Instant};
+
+/// Which hand a press belongs to, so each side of the keyboard or gamepad can
+/// follow its own grid in polyrhythm mode.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+impl Hand {
+    pub fn index(&self) -> usize {
+        match self {
+            Hand::Left => 0,
+            Hand::Right => 1,
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            Hand::Left => "L",
+            Hand::Right => "R",
+        }
+    }
+
+    fn of_key(key: KeyCode) -> Self {
+        if LEFT_KEYS.contains(&key) {
+            Hand::Left
+        } else {
+            Hand::Right
+        }
+    }
+
+    fn of_gamepad_button(button: GamepadButton) -> Self {
+        match button {
+            GamepadButton::LeftTrigger
+            | GamepadButton::LeftTrigger2
+            | GamepadButton::LeftThumb
+            | GamepadButton::DPadUp
+            | GamepadButton::DPadDown
+            | GamepadButton::DPadLeft
+            | GamepadButton::DPadRight
+            | GamepadButton::Select => Hand::Left,
+            _ => Hand::Right,
+        }
+    }
+}
+
+/// The left half of the keyboard, every other key taps with the right hand.
+const LEFT_KEYS: &[KeyCode] = &[
+    KeyCode::Backquote,
+    KeyCode::Tab,
+    KeyCode::CapsLock,
+    KeyCode::ShiftLeft,
+    KeyCode::ControlLeft,
+    KeyCode::AltLeft,
+    KeyCode::KeyQ,
+    KeyCode::KeyW,
+    KeyCode::KeyE,
+    KeyCode::KeyR,
+    KeyCode::KeyA,
+    KeyCode::KeyS,
+    KeyCode::KeyD,
+    KeyCode::KeyF,
+    KeyCode::KeyZ,
+    KeyCode::KeyX,
+    KeyCode::KeyC,
+    KeyCode::KeyV,
+];
+
+/// Switches [`KeyboardTaps`], the only key that never taps.
+pub const KEYBOARD_TAPS_KEY: KeyCode = KeyCode::Escape;
+
+/// Keys of the shortcuts in the `*_control` systems, including Shift for the fine
+/// tempo steps. They only tap with [`KeyboardTaps`] on.
+const SHORTCUT_KEYS: &[KeyCode] = &[
+    KeyCode::ShiftLeft,
+    KeyCode::ShiftRight,
+    KeyCode::Enter,
+    KeyCode::ArrowUp,
+    KeyCode::ArrowDown,
+    KeyCode::ArrowLeft,
+    KeyCode::ArrowRight,
+    KeyCode::BracketLeft,
+    KeyCode::BracketRight,
+    KeyCode::Minus,
+    KeyCode::Equal,
+    KeyCode::Comma,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+    KeyCode::KeyA,
+    KeyCode::KeyB,
+    KeyCode::KeyC,
+    KeyCode::KeyE,
+    KeyCode::KeyF,
+    KeyCode::KeyG,
+    KeyCode::KeyH,
+    KeyCode::KeyI,
+    KeyCode::KeyJ,
+    KeyCode::KeyK,
+    KeyCode::KeyL,
+    KeyCode::KeyM,
+    KeyCode::KeyN,
+    KeyCode::KeyO,
+    KeyCode::KeyP,
+    KeyCode::KeyR,
+    KeyCode::KeyS,
+    KeyCode::KeyT,
+    KeyCode::KeyU,
+    KeyCode::KeyV,
+    KeyCode::KeyW,
+    KeyCode::KeyY,
+];
+
+/// Whether every key taps. Off, the shortcut keys control the trainer and every
+/// other key taps. On, the shortcuts are off so any key can tap.
+#[derive(Resource, Default)]
+pub struct KeyboardTaps(pub bool);
+
+impl KeyboardTaps {
+    pub fn label(&self) -> &str {
+        if self.0 {
+            "every key"
+        } else {
+            "all but shortcuts"
+        }
+    }
+
+    fn taps(&self, key: KeyCode) -> bool {
+        key != KEYBOARD_TAPS_KEY && (self.0 || !SHORTCUT_KEYS.contains(&key))
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Press {
+    pub time: Instant,
+    pub hand: Hand,
+    // A key that only taps with `KeyboardTaps` on
+    shortcut: bool,
+}
+
+/// Presses timestamped off the frame loop, as close to the OS event as possible.
+///
+/// Devices covered here are skipped by the frame-polled path in [`TapInputs`], which
+/// only knows that a press happened somewhere between two frames.
+#[derive(Resource, Clone, Default)]
+pub struct PressTimestamps {
+    presses: Arc<Mutex<Vec<Press>>>,
+    pub gamepad: bool,
+    pub keyboard_and_pointer: bool,
+}
+
+impl PressTimestamps {
+    pub fn start() -> Self {
+        let mut timestamps = Self::default();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            timestamps.gamepad = spawn_gamepad_thread(timestamps.presses.clone());
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            timestamps.keyboard_and_pointer = add_dom_listeners(timestamps.presses.clone());
+        }
+
+        timestamps
+    }
+
+    /// Presses received since the last call.
+    pub fn drain(&self) -> Vec<Press> {
+        std::mem::take(&mut *self.presses.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+/// Every input that counts as a tap.
+#[derive(SystemParam)]
+pub struct TapInputs<'w, 's> {
+    keyboard_input: Res<'w, ButtonInput<KeyCode>>,
+    game_pad: Query<'w, 's, &'static Gamepad>,
+    buttons: Res<'w, ButtonInput<MouseButton>>,
+    touches: Res<'w, Touches>,
+    windows: Query<'w, 's, &'static Window>,
+    press_timestamps: Res<'w, PressTimestamps>,
+    keyboard_taps: Res<'w, KeyboardTaps>,
+    real_time: Res<'w, Time<Real>>,
+}
+
+impl TapInputs<'_, '_> {
+    /// The presses since the last frame, in order.
+    pub fn presses(&self) -> Vec<Press> {
+        let mut presses = self.press_timestamps.drain();
+        presses.retain(|press| self.keyboard_taps.0 || !press.shortcut);
+        presses.extend(self.frame_polled_presses());
+        presses.sort_by_key(|press| press.time);
+        presses
+    }
+
+    /// Presses of the devices [`PressTimestamps`] doesn't cover, at [`frame_estimate`].
+    ///
+    /// This is every native keyboard, mouse and touch press: winit doesn't report
+    /// when its input events happened, so those taps can be off by up to half a
+    /// frame. Only gamepads on native and everything on the web are timestamped.
+    fn frame_polled_presses(&self) -> Vec<Press> {
+        let mut hands = Vec::new();
+        if !self.press_timestamps.keyboard_and_pointer {
+            hands.extend(
+                self.keyboard_input
+                    .get_just_pressed()
+                    .filter(|key| self.keyboard_taps.taps(**key))
+                    .map(|key| Hand::of_key(*key)),
+            );
+            hands.extend(self.buttons.get_just_pressed().map(|button| {
+                if *button == MouseButton::Left {
+                    Hand::Left
+                } else {
+                    Hand::Right
+                }
+            }));
+            let width = self
+                .windows
+                .iter()
+                .next()
+                .map_or(0.0, |window| window.width());
+            hands.extend(self.touches.iter_just_pressed().map(|touch| {
+                if touch.position().x < width / 2.0 {
+                    Hand::Left
+                } else {
+                    Hand::Right
+                }
+            }));
+        }
+        if !self.press_timestamps.gamepad {
+            for game_pad in &self.game_pad {
+                hands.extend(
+                    game_pad
+                        .get_just_pressed()
+                        .map(|button| Hand::of_gamepad_button(*button)),
+                );
+            }
+        }
+
+        // One press per hand, the frame can't tell simultaneous presses apart
+        let time = frame_estimate(&self.real_time);
+        [Hand::Left, Hand::Right]
+            .into_iter()
+            .filter(|hand| hands.contains(hand))
+            .map(|hand| Press {
+                time,
+                hand,
+                shortcut: false,
+            })
+            .collect()
+    }
+}
+
+/// Best guess for a press seen by frame polling: the middle of the last frame.
+fn frame_estimate(time: &Time<Real>) -> Instant {
+    let now = Instant::now();
+    let last_update = time.last_update().unwrap_or(now);
+    last_update
+        .checked_sub(time.delta() / 2)
+        .unwrap_or(last_update)
+}
+
+fn push(presses: &Mutex<Vec<Press>>, age: Duration, hand: Hand, shortcut: bool) {
+    let now = Instant::now();
+    presses
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(Press {
+            time: now.checked_sub(age).unwrap_or(now),
+            hand,
+            shortcut,
+        });
+}
+
+// gilrs reports the time the driver saw the event, but bevy_gilrs drops it,
+// so a second instance is read on its own thread.
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn_gamepad_thread(presses: Arc<Mutex<Vec<Press>>>) -> bool {
+    use gilrs::{EventType, Filter, GilrsBuilder, ev::filter::axis_dpad_to_button};
+    use std::time::SystemTime;
+
+    let (started, receiver) = std::sync::mpsc::channel();
+
+    let spawned = std::thread::Builder::new()
+        .name("gamepad timestamps".to_string())
+        .spawn(move || {
+            let mut gilrs = match GilrsBuilder::new()
+                .with_default_filters(false)
+                .set_update_state(false)
+                .build()
+            {
+                Ok(gilrs) => gilrs,
+                Err(err) => {
+                    warn!("Gamepad timestamps unavailable: {err}");
+                    let _ = started.send(false);
+                    return;
+                }
+            };
+            let _ = started.send(true);
+
+            loop {
+                let Some(event) = gilrs.next_event_blocking(None) else {
+                    continue;
+                };
+                let Some(event) = Some(event).filter_ev(&axis_dpad_to_button, &mut gilrs) else {
+                    continue;
+                };
+                if let EventType::ButtonPressed(button, _) = event.event {
+                    let age = SystemTime::now()
+                        .duration_since(event.time)
+                        .unwrap_or_default();
+                    let hand = convert_button(button).map_or(Hand::Right, Hand::of_gamepad_button);
+                    push(&presses, age, hand, false);
+                }
+            }
+        });
+
+    spawned.is_ok() && receiver.recv().unwrap_or(false)
+}
+
+// The same mapping as bevy_gilrs, which keeps its own private
+#[cfg(not(target_arch = "wasm32"))]
+fn convert_button(button: gilrs::Button) -> Option<GamepadButton> {
+    use gilrs::Button;
+
+    match button {
+        Button::South => Some(GamepadButton::South),
+        Button::East => Some(GamepadButton::East),
+        Button::North => Some(GamepadButton::North),
+        Button::West => Some(GamepadButton::West),
+        Button::C => Some(GamepadButton::C),
+        Button::Z => Some(GamepadButton::Z),
+        Button::LeftTrigger => Some(GamepadButton::LeftTrigger),
+        Button::LeftTrigger2 => Some(GamepadButton::LeftTrigger2),
+        Button::RightTrigger => Some(GamepadButton::RightTrigger),
+        Button::RightTrigger2 => Some(GamepadButton::RightTrigger2),
+        Button::Select => Some(GamepadButton::Select),
+        Button::Start => Some(GamepadButton::Start),
+        Button::Mode => Some(GamepadButton::Mode),
+        Button::LeftThumb => Some(GamepadButton::LeftThumb),
+        Button::RightThumb => Some(GamepadButton::RightThumb),
+        Button::DPadUp => Some(GamepadButton::DPadUp),
+        Button::DPadDown => Some(GamepadButton::DPadDown),
+        Button::DPadLeft => Some(GamepadButton::DPadLeft),
+        Button::DPadRight => Some(GamepadButton::DPadRight),
+        Button::Unknown => None,
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn add_dom_listeners(presses: Arc<Mutex<Vec<Press>>>) -> bool {
+    use wasm_bindgen::{JsCast, closure::Closure};
+    use web_sys::{Event, KeyboardEvent, PointerEvent};
+
+    let Some(window) = web_sys::window() else {
+        return false;
+    };
+    let Some(performance) = window.performance() else {
+        return false;
+    };
+
+    // `timeStamp` shares its time origin with `performance.now()`
+    let width = window.clone();
+    let listener = Closure::<dyn FnMut(Event)>::new(move |event: Event| {
+        let (hand, shortcut) = if let Some(event) = event.dyn_ref::<KeyboardEvent>() {
+            if event.repeat() {
+                return;
+            }
+            // The names of `KeyCode` follow the DOM `code` values
+            let is_one_of =
+                |keys: &[KeyCode]| keys.iter().any(|key| format!("{key:?}") == event.code());
+            if is_one_of(&[KEYBOARD_TAPS_KEY]) {
+                return;
+            }
+            let hand = if is_one_of(LEFT_KEYS) {
+                Hand::Left
+            } else {
+                Hand::Right
+            };
+            // Whether it taps depends on `KeyboardTaps`, checked when drained
+            (hand, is_one_of(SHORTCUT_KEYS))
+        } else if let Some(event) = event.dyn_ref::<PointerEvent>() {
+            let left = if event.pointer_type() == "touch" {
+                let width = width
+                    .inner_width()
+                    .ok()
+                    .and_then(|w| w.as_f64())
+                    .unwrap_or(0.0);
+                (event.client_x() as f64) < width / 2.0
+            } else {
+                event.button() == 0
+            };
+            (if left { Hand::Left } else { Hand::Right }, false)
+        } else {
+            (Hand::Right, false)
+        };
+        let age_ms = (performance.now() - event.time_stamp()).max(0.0);
+        push(
+            &presses,
+            Duration::from_secs_f64(age_ms / 1000.0),
+            hand,
+            shortcut,
+        );
+    });
+
+    let added = ["keydown", "pointerdown"].iter().all(|name| {
+        window
+            .add_event_listener_with_callback(name, listener.as_ref().unchecked_ref())
+            .is_ok()
+    });
+    listener.forget();
+
+    added
+}
@@ -0,0 +1,164 @@
+use bevy::prelude::*;
+
+use crate::text_entry::{TextEntered, TextTarget};
+
+/// How close a tap was to its onset, from best to worst.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Tier {
+    Perfect,
+    Great,
+    Good,
+    Bad,
+    Miss,
+}
+
+impl Tier {
+    pub const ALL: [Tier; 5] = [
+        Tier::Perfect,
+        Tier::Great,
+        Tier::Good,
+        Tier::Bad,
+        Tier::Miss,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Tier::Perfect => "Perfect",
+            Tier::Great => "Great",
+            Tier::Good => "Good",
+            Tier::Bad => "Bad",
+            Tier::Miss => "Miss",
+        }
+    }
+}
+
+/// Tiers that have a window, everything outside the last one is a [`Tier::Miss`].
+pub const WINDOWED_TIERS: usize = 4;
+
+/// Windows cycled through by the judgement button, as (name, ± milliseconds of
+/// Perfect, Great, Good and Bad). They follow the timing of those games.
+pub const PRESETS: &[(&str, [f64; WINDOWED_TIERS])] = &[
+    (
+        "Trainer",
+        [1000.0 / 60.0, 1500.0 / 60.0, 2000.0 / 60.0, 3000.0 / 60.0],
+    ),
+    ("DDR", [16.7, 33.3, 91.7, 141.7]),
+    ("StepMania J4", [22.5, 45.0, 90.0, 135.0]),
+    ("osu!mania OD8", [16.0, 40.0, 73.0, 103.0]),
+    ("beatmania IIDX", [16.7, 33.3, 116.7, 250.0]),
+];
+
+/// Windows a tap's |delta| is judged with.
+#[derive(Resource)]
+pub struct Judgement {
+    /// Index into [`PRESETS`], `None` for typed windows.
+    preset: Option<usize>,
+    /// ± seconds, increasing.
+    windows: [f64; WINDOWED_TIERS],
+}
+
+impl Default for Judgement {
+    fn default() -> Self {
+        let mut judgement = Self {
+            preset: None,
+            windows: [0.0; WINDOWED_TIERS],
+        };
+        judgement.select(0);
+        judgement
+    }
+}
+
+impl Judgement {
+    pub fn tier(&self, delta: f64) -> Tier {
+        self.windows
+            .iter()
+            .position(|window| delta.abs() <= *window)
+            .map_or(Tier::Miss, |i| Tier::ALL[i])
+    }
+
+    /// ± seconds of `tier`, `None` for [`Tier::Miss`].
+    pub fn window(&self, tier: Tier) -> Option<f64> {
+        let i = Tier::ALL.iter().position(|t| *t == tier)?;
+        self.windows.get(i).copied()
+    }
+
+    fn select(&mut self, preset: usize) {
+        self.preset = Some(preset);
+        self.windows = PRESETS[preset].1.map(|ms| ms / 1000.0);
+    }
+
+    /// Switches to the next of [`PRESETS`], back to the first after the last one.
+    pub fn next(&mut self) {
+        self.select(self.preset.map_or(0, |i| (i + 1) % PRESETS.len()));
+    }
+
+    /// Sets the windows from milliseconds separated by spaces or commas, ignores
+    /// anything that isn't [`WINDOWED_TIERS`] increasing windows.
+    pub fn set_windows(&mut self, text: &str) {
+        let Ok(windows) = text
+            .split([' ', ','])
+            .filter(|ms| !ms.is_empty())
+            .map(|ms| ms.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+        else {
+            return;
+        };
+        let Ok(windows) = <[f64; WINDOWED_TIERS]>::try_from(windows) else {
+            return;
+        };
+        if windows[0] > 0.0 && windows.windows(2).all(|pair| pair[0] < pair[1]) {
+            self.preset = None;
+            self.windows = windows.map(|ms| ms / 1000.0);
+        }
+    }
+
+    /// The windows in milliseconds, as typed into [`Judgement::set_windows`].
+    pub fn windows_text(&self) -> String {
+        self.windows
+            .iter()
+            .map(|window| format!("{}", (window * 10000.0).round() / 10.0))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn label(&self) -> &str {
+        self.preset.map_or("Custom", |i| PRESETS[i].0)
+    }
+}
+
+pub fn judgement_windows(mut entered: EventReader<TextEntered>, mut judgement: ResMut<Judgement>) {
+    for TextEntered(target, text) in entered.read() {
+        if *target == TextTarget::JudgementWindows {
+            judgement.set_windows(text);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_windows_must_be_four_increasing_milliseconds() {
+        let mut judgement = Judgement::default();
+        judgement.set_windows("20, 40,80 120");
+        assert_eq!(judgement.windows_text(), "20 40 80 120");
+        assert_eq!(judgement.label(), "Custom");
+        assert_eq!(judgement.tier(0.03), Tier::Great);
+        assert_eq!(judgement.tier(-0.121), Tier::Miss);
+
+        for text in [
+            "10 20 30",
+            "10 20 30 40 50",
+            "10 30 20 40",
+            "10 10 20 30",
+            "0 10 20 30",
+        ] {
+            judgement.set_windows(text);
+            assert_eq!(judgement.windows_text(), "20 40 80 120", "{text}");
+        }
+        judgement.set_windows("-5 10 20 30");
+        judgement.set_windows("5 10 20 x");
+        assert_eq!(judgement.windows_text(), "20 40 80 120");
+    }
+}
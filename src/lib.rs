@@ -3,40 +3,180 @@ use std::collections::VecDeque;
 use bevy::utils::{Duration, Instant};
 
 use bevy::{
+    audio::AddAudioSource,
     color::palettes::basic::*,
     diagnostic::{DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin},
+    ecs::system::SystemParam,
     prelude::*,
     render::{camera::ScalingMode, mesh::CircleMeshBuilder},
 };
 
+use beat_grid::BeatGrid;
+use calibration::{CALIBRATION_TAPS, Calibration, CalibrationKind, LatencyOffset};
+use ceiling_finder::CeilingFinder;
+use click_track::{ClickTrack, ScheduledClick};
+use dropout::ClickDropout;
+use fade::ClickFade;
+use free_running::FreeRunning;
+use gap_click::GapClick;
+use input_time::{Hand, KEYBOARD_TAPS_KEY, KeyboardTaps, PressTimestamps, TapInputs};
+use judgement::{Judgement, Tier};
+use meter::{AccentLevel, TimeSignature};
+use miss::MissDetector;
+use pattern::Patterns;
+use pattern_editor::{EditorPanel, PatternEditor};
+use polyrhythm::Polyrhythm;
+use popup::{Combo, ComboText};
+use session::Session;
+use speed_trainer::SpeedTrainer;
+use tap_tempo::TapTempo;
+use text_entry::{TextEntered, TextEntry, TextTarget};
+use totals::Totals;
+
+mod beat_grid;
+mod calibration;
+mod ceiling_finder;
+mod click_track;
+mod dropout;
+mod fade;
+mod free_running;
+mod gap_click;
+mod input_time;
+mod judgement;
+mod meter;
+mod miss;
+mod pattern;
+mod pattern_editor;
+mod polyrhythm;
+mod popup;
+mod session;
+mod speed_trainer;
+mod stats;
+mod storage;
+mod tap_tempo;
+mod text_entry;
+mod totals;
+
 const CIRCLE_SIZE: f32 = 400.0;
 const BINS: usize = 16;
 
 const BAR_HEIGHT_MULTIPLIER: f32 = 4000.0;
 
+// How far ahead of the audio thread clicks are queued
+const SCHEDULE_AHEAD: Duration = Duration::from_millis(200);
+
 #[derive(Component)]
 struct StatusText;
 
 #[derive(Component)]
 struct ClockMarker;
 
-#[derive(Resource)]
-struct LastTick(Instant);
+// The next beat of the grid to queue on the click track
+#[derive(Resource, Default)]
+struct MetronomeSchedule {
+    next_beat: Option<i64>,
+}
 
 #[derive(Resource)]
 struct Division(u32);
 
+// Tempo as set, the period of the beat grid is derived from it
+#[derive(Resource)]
+struct Bpm(f32);
+
+// Tempos the metronome can keep, a faster one would queue more clicks than it can play
+const MIN_BPM: f32 = 1.0;
+const MAX_BPM: f32 = 1000.0;
+
+impl Bpm {
+    // Steps to the nearest tenth, within MIN_BPM and MAX_BPM
+    fn step(&mut self, step: f32) {
+        self.0 = (((self.0 + step) * 10.0).round() / 10.0).clamp(MIN_BPM, MAX_BPM);
+    }
+
+    // A typed tempo, `None` when it isn't a number within MIN_BPM and MAX_BPM
+    fn parse(text: &str) -> Option<f32> {
+        text.trim()
+            .parse::<f32>()
+            .ok()
+            .filter(|bpm| (MIN_BPM..=MAX_BPM).contains(bpm))
+    }
+
+    fn label(&self) -> String {
+        format!("{}", (self.0 * 10.0).round() / 10.0)
+    }
+}
+
+#[derive(Clone)]
 struct Delta {
     delta: f64,
     division: usize,
+    // pulse of the bar of the nearest division
+    pulse: u32,
     // 0 to 2pi
     theta: f64,
+    // grid of the polyrhythm the tap was judged against
+    hand: Option<Hand>,
+    // whether the metronome ticked on the beat of the tap
+    audible: bool,
+    // volume factor of the metronome on the beat of the tap
+    volume: f32,
+    // position of the onset on the grid, of the press without a grid
+    position: f64,
+    // no tap for the onset before its late window closed
+    missed: bool,
+    // when the key was pressed, when the onset was heard for a miss
+    time: Instant,
+}
+
+impl Delta {
+    fn tier(&self, judgement: &Judgement) -> Tier {
+        if self.missed {
+            Tier::Miss
+        } else {
+            judgement.tier(self.delta)
+        }
+    }
+}
+
+#[cfg(test)]
+impl Delta {
+    /// A tap `delta` seconds off the onset at `position` of the grid of `hand`.
+    fn tap(hand: Option<Hand>, position: f64, delta: f64) -> Self {
+        Self {
+            delta,
+            division: 0,
+            pulse: 0,
+            theta: 0.0,
+            hand,
+            audible: true,
+            volume: 1.0,
+            position,
+            missed: false,
+            time: Instant::now(),
+        }
+    }
+
+    fn miss(hand: Option<Hand>, position: f64) -> Self {
+        Self {
+            missed: true,
+            ..Self::tap(hand, position, 0.0)
+        }
+    }
 }
 
 #[derive(Resource)]
 // delta and nearest disvision
 struct TapDeltas(VecDeque<Delta>);
 
+// Sent for every judged tap, TapDeltas only keeps the latest ones
+#[derive(Event)]
+struct Tapped(Delta);
+
+// Sent for every onset recorded as missed
+#[derive(Event)]
+struct Missed(Delta);
+
 #[derive(Resource, Default)]
 struct Mute {
     tick_mute: bool,
@@ -54,13 +194,13 @@ struct AudioHandles {
     handles: Vec<Handle<AudioSource>>,
     tick: usize,
     tap: usize,
+    // tick on the first beat of the bar
+    accent: usize,
+    // clicks of the polyrhythm grids, by hand
+    hands: [usize; 2],
 }
 
 impl AudioHandles {
-    fn tick(&self) -> &Handle<AudioSource> {
-        &self.handles[self.tick]
-    }
-
     fn tap(&self) -> &Handle<AudioSource> {
         &self.handles[self.tap]
     }
@@ -74,12 +214,15 @@ struct ClockResource {
     material_delta: Handle<ColorMaterial>,
     mesh_precision: Handle<Mesh>,
     material_precision: Handle<ColorMaterial>,
+    // legend of the polyrhythm grids, by hand
+    material_hands: [Handle<ColorMaterial>; 2],
 }
 
 #[derive(Component)]
 enum Index {
     Tick,
     Tap,
+    Accent,
 }
 
 #[derive(Component)]
@@ -88,6 +231,8 @@ enum IndexButton {
     TickDecrement,
     TapIncrement,
     TapDecrement,
+    AccentIncrement,
+    AccentDecrement,
 }
 
 #[derive(Component)]
@@ -98,19 +243,48 @@ pub struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((FrameTimeDiagnosticsPlugin, EntityCountDiagnosticsPlugin))
-            .insert_resource(Time::<Fixed>::from_duration(from_bpm(90.0)))
-            .insert_resource(LastTick(Instant::now()))
+            .insert_resource(Bpm(90.0))
+            .insert_resource(BeatGrid::new(
+                Instant::now() + SCHEDULE_AHEAD,
+                from_bpm(90.0),
+            ))
+            .insert_resource(MetronomeSchedule::default())
             .insert_resource(Division(1))
+            .insert_resource(TimeSignature::default())
             .insert_resource(TapDeltas(VecDeque::new()))
             .insert_resource(Mute::default())
+            .insert_resource(PressTimestamps::start())
+            .insert_resource(KeyboardTaps::default())
+            .insert_resource(LatencyOffset::default())
+            .insert_resource(Calibration::default())
             .insert_resource(HideBarChart(false))
             .insert_resource(HideClock(false))
+            .insert_resource(SpeedTrainer::default())
+            .insert_resource(CeilingFinder::default())
+            .insert_resource(Polyrhythm::default())
+            .insert_resource(GapClick::default())
+            .insert_resource(ClickDropout::default())
+            .insert_resource(ClickFade::default())
+            .insert_resource(Patterns::load())
+            .insert_resource(PatternEditor::default())
+            .insert_resource(TextEntry::default())
+            .insert_resource(TapTempo::default())
+            .insert_resource(FreeRunning::default())
+            .insert_resource(Judgement::default())
+            .insert_resource(Combo::default())
+            .insert_resource(MissDetector::default())
+            .insert_resource(Session::default())
+            .insert_resource(Totals::default())
+            .add_event::<Tapped>()
+            .add_event::<Missed>()
+            .add_event::<TextEntered>()
+            .add_audio_source::<ClickTrack>()
             .add_systems(Startup, setup)
-            .add_systems(FixedUpdate, metronome)
             .add_systems(
                 Update,
                 (
-                    control,
+                    load_click_sounds,
+                    (sync_beat_grid, metronome).chain(),
                     clock,
                     set_status_text,
                     set_bins,
@@ -122,17 +296,55 @@ impl Plugin for GamePlugin {
                     set_audio_indices,
                     set_statistics,
                     set_clock_delta,
+                    set_accent_buttons,
+                    accent_button_system,
+                    (
+                        tempo_control,
+                        meter_control,
+                        click_control,
+                        judgement_control,
+                        view_control,
+                    ),
+                    set_guide_lines,
                 ),
             )
             // Set tap sound before tap
-            .add_systems(Update, (index_button_system, tap).chain());
+            .add_systems(
+                Update,
+                (
+                    index_button_system,
+                    tap,
+                    miss::detect_misses,
+                    session::session,
+                    speed_trainer::speed_trainer,
+                    ceiling_finder::ceiling_finder,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    popup::judgement_popups,
+                    popup::animate_popups,
+                    popup::set_combo_text,
+                    (totals::count_totals, set_bias_needle).chain(),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    text_entry::text_entry,
+                    dropout::dropout_seed,
+                    bpm_entry,
+                    judgement::judgement_windows,
+                    pattern_editor::pattern_editor_buttons,
+                    pattern_editor::set_pattern_editor,
+                )
+                    .chain(),
+            );
     }
 }
 
-fn bpm(time: &Time<Fixed>) -> f32 {
-    60.0 / time.timestep().as_secs_f32()
-}
-
 fn from_bpm(bpm: f32) -> Duration {
     Duration::from_secs_f32(60.0 / bpm)
 }
@@ -140,33 +352,85 @@ fn from_bpm(bpm: f32) -> Duration {
 const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
 const PRESSED_BUTTON: Color = Color::srgb(0.35, 0.75, 0.35);
 
-#[derive(Component, Clone, Copy)]
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
 enum ButtonKind {
+    BpmUpTenth,
+    BpmDownTenth,
+    BpmEntry,
     BpmUp1,
     BpmDown1,
     BpmUp10,
     BpmDown10,
     DivisionUp1,
     DivisionDown1,
+    BeatsPerBarUp1,
+    BeatsPerBarDown1,
+    Meter,
+    Polyrhythm,
+    Pattern,
+    Editor,
+    Swing,
+    GapClick,
+    Dropout,
+    DropoutSeed,
+    Fade,
+    FadeBackUp,
+    TapTempo,
+    FreeRunning,
+    Judgement,
+    JudgementWindows,
+    Session,
     TapMute,
     TickMute,
     HideClock,
     HideBarChart,
+    KeyboardTaps,
+    SpeedTrainer,
+    SpeedTrainerInterval,
+    CeilingFinder,
+    AudioCalibrate,
+    VisualCalibrate,
 }
 
 impl ButtonKind {
     fn label(&self) -> &str {
         match self {
+            ButtonKind::BpmUpTenth => "BPM+0.1",
+            ButtonKind::BpmDownTenth => "BPM-0.1",
+            ButtonKind::BpmEntry => "BPM...",
             ButtonKind::BpmUp1 => "BPM+1",
             ButtonKind::BpmDown1 => "BPM-1",
             ButtonKind::BpmUp10 => "BPM+10",
             ButtonKind::BpmDown10 => "BPM-10",
             ButtonKind::DivisionUp1 => "Div+",
             ButtonKind::DivisionDown1 => "Div-",
+            ButtonKind::BeatsPerBarUp1 => "Beats+",
+            ButtonKind::BeatsPerBarDown1 => "Beats-",
+            ButtonKind::Meter => "Meter",
+            ButtonKind::Polyrhythm => "Poly",
+            ButtonKind::Pattern => "Pattern",
+            ButtonKind::Editor => "Editor",
+            ButtonKind::Swing => "Swing",
+            ButtonKind::GapClick => "Gap",
+            ButtonKind::Dropout => "Dropout",
+            ButtonKind::DropoutSeed => "Seed",
+            ButtonKind::Fade => "Fade",
+            ButtonKind::FadeBackUp => "Fade Back",
+            ButtonKind::TapTempo => "Tap Tempo",
+            ButtonKind::FreeRunning => "Free Run",
+            ButtonKind::Judgement => "Judge",
+            ButtonKind::JudgementWindows => "Windows",
+            ButtonKind::Session => "Session",
             ButtonKind::TapMute => "Tap Mute",
             ButtonKind::TickMute => "Tick Mute",
             ButtonKind::HideClock => "Clock",
             ButtonKind::HideBarChart => "Chart",
+            ButtonKind::KeyboardTaps => "Key Taps",
+            ButtonKind::SpeedTrainer => "Trainer",
+            ButtonKind::SpeedTrainerInterval => "Bars/Taps",
+            ButtonKind::CeilingFinder => "Ceiling",
+            ButtonKind::AudioCalibrate => "Audio Cal",
+            ButtonKind::VisualCalibrate => "Visual Cal",
         }
     }
 }
@@ -181,8 +445,13 @@ fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    mut click_tracks: ResMut<Assets<ClickTrack>>,
     asset_server: Res<AssetServer>,
 ) {
+    let click_track = ClickTrack::default();
+    commands.spawn(AudioPlayer(click_tracks.add(click_track.clone())));
+    commands.insert_resource(click_track);
+
     commands.insert_resource(AudioHandles {
         handles: vec![
             asset_server.load("sounds/c4.ogg"),
@@ -193,6 +462,8 @@ fn setup(
         ],
         tap: 0,
         tick: 1,
+        accent: 3,
+        hands: [2, 4],
     });
 
     commands.insert_resource(ClockResource {
@@ -204,6 +475,10 @@ fn setup(
             half_size: Vec2::new(0.5, 0.5),
         })),
         material_precision: materials.add(Color::linear_rgb(0.0, 0.0, 0.0)),
+        material_hands: [
+            materials.add(Color::linear_rgb(0.4, 0.2, 0.0)),
+            materials.add(Color::linear_rgb(0.25, 0.05, 0.35)),
+        ],
     });
 
     commands.spawn((
@@ -244,6 +519,24 @@ fn setup(
                 MeshMaterial2d(materials.add(Color::BLACK)),
                 Transform::from_xyz(0.0, 0.0, 1.0),
             ));
+
+            commands.spawn((
+                BiasNeedle,
+                Mesh2d(meshes.add(Rectangle::new(4.0, CIRCLE_SIZE))),
+                MeshMaterial2d(materials.add(Color::srgb(0.9, 0.2, 0.6))),
+                Transform::default(),
+                Visibility::Hidden,
+            ));
+
+            commands.spawn((
+                ComboText,
+                Text2d::default(),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+                Transform::from_xyz(0.0, -CIRCLE_SIZE * 0.3, 10.0),
+            ));
         });
 
     commands.spawn(
@@ -389,6 +682,77 @@ fn setup(
                         Text::new(""),
                     ));
                 });
+                commands.spawn((
+                    Node {
+                        border: UiRect::all(Val::Px(2.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        margin: UiRect {
+                            top: Val::Px(2.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    BorderColor(Color::BLACK),
+                    BackgroundColor(NORMAL_BUTTON),
+                )).with_children(|commands| {
+                    commands.spawn((
+                        Button,
+                        IndexButton::AccentDecrement,
+                        Node {
+                            position_type: PositionType::Absolute,
+                            left: Val::Px(0.0),
+                            height: Val::Percent(100.0),
+                            width: Val::Percent(50.0),
+                            ..default()
+                        },
+                    )).with_children(|commands| {
+                        commands.spawn((
+                            Text::new("-"),
+                            Node {
+                                position_type: PositionType::Absolute,
+                                left: Val::Px(12.0),
+                                ..default()
+                            }
+                        ));
+                    });
+                    commands.spawn((
+                        Button,
+                        IndexButton::AccentIncrement,
+                        Node {
+                            position_type: PositionType::Absolute,
+                            right: Val::Px(0.0),
+                            height: Val::Percent(100.0),
+                            width: Val::Percent(50.0),
+                            ..default()
+                        },
+                    )).with_children(|commands| {
+                        commands.spawn((
+                            Text::new("+"),
+                            Node {
+                                position_type: PositionType::Absolute,
+                                right: Val::Px(12.0),
+                                ..default()
+                            }
+                        ));
+                    });
+                    commands.spawn((
+                        Index::Accent,
+                        Text::new(""),
+                    ));
+                });
+                commands.spawn((
+                    AccentButtons,
+                    Node {
+                        display: Display::Flex,
+                        flex_direction: FlexDirection::Row,
+                        margin: UiRect {
+                            top: Val::Px(2.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                ));
             }
         );
 
@@ -410,7 +774,7 @@ fn setup(
         #[cfg(not(target_os = "android"))]
         commands.spawn((
             Text::new(
-                "up/down: BPM +-1\nleft/right: BPM +-10\n[/]: Division +-1\nn: Tap Mute\nm: Tick Mute\n,: Hide Clock",
+                "up/down: BPM +-1\nshift+up/down: BPM +-0.1\nleft/right: BPM +-10\nenter: Type BPM\n[/]: Division +-1\n-/=: Beats per bar +-1\ng: Meter grouping\np: Polyrhythm\no: Pattern\ne: Pattern Editor\ns: Swing\nk: Gap Click\nj: Click Dropout\nu: Dropout Seed\nl: Click Fade\ni: Fade Back Up\nb: Tap Tempo\nr: Free Running\nh: Judgement Preset\nw: Judgement Windows\na: Start/End Session\n1-9: Beat accent\nn: Tap Mute\nm: Tick Mute\n,: Hide Clock\nt: Speed Trainer\ny: Speed Trainer Bars/Taps\nf: Find BPM Ceiling\nc: Audio Calibration\nv: Visual Calibration",
             ),
             Node {
                 margin: UiRect {
@@ -448,20 +812,23 @@ fn setup(
                     ..Default::default()
                 })
                 .with_children(|commands| {
-                    for (f, height, label) in [
-                        (0.0, 4.0, "0"),
-                        (1.0, 3.0, "1/60"),
-                        (1.5, 2.0, "1.5/60"),
-                        (2.0, 1.0, "2/60"),
+                    // Sized by set_guide_lines
+                    for (tier, border) in [
+                        (None, 4.0),
+                        (Some(Tier::Perfect), 3.0),
+                        (Some(Tier::Great), 2.0),
+                        (Some(Tier::Good), 1.5),
+                        (Some(Tier::Bad), 1.0),
                     ] {
+                        let line = GuideLine { tier, border };
                         commands.spawn((
+                            line,
                             Node {
                                 position_type: PositionType::Absolute,
                                 width: Val::Percent(100.0),
-                                height: Val::Px(f / 60.0 * BAR_HEIGHT_MULTIPLIER + height / 2.0),
                                 bottom: Val::Percent(50.0),
                                 border: UiRect {
-                                    top: Val::Px(height),
+                                    top: Val::Px(border),
                                     ..default()
                                 },
                                 ..default()
@@ -469,13 +836,13 @@ fn setup(
                             BorderColor(Color::BLACK),
                         ));
                         commands.spawn((
+                            line,
                             Node {
                                 position_type: PositionType::Absolute,
                                 width: Val::Percent(100.0),
-                                height: Val::Px(f / 60.0 * BAR_HEIGHT_MULTIPLIER + height / 2.0),
                                 top: Val::Percent(50.0),
                                 border: UiRect {
-                                    bottom: Val::Px(height),
+                                    bottom: Val::Px(border),
                                     ..default()
                                 },
                                 ..default()
@@ -485,10 +852,10 @@ fn setup(
 
                         commands
                             .spawn((
+                                GuideLine { tier, border: 0.0 },
                                 Node {
                                     position_type: PositionType::Absolute,
                                     left: Val::Px(-12.0),
-                                    height: Val::Px(f / 60.0 * BAR_HEIGHT_MULTIPLIER),
                                     width: Val::Percent(100.0),
                                     bottom: Val::Percent(50.0),
                                     ..default()
@@ -497,13 +864,14 @@ fn setup(
                             ))
                             .with_children(|commands| {
                                 commands.spawn((
+                                    GuideLabel(tier),
                                     Node {
                                         position_type: PositionType::Absolute,
                                         right: Val::Percent(100.0),
                                         bottom: Val::Percent(100.0),
                                         ..default()
                                     },
-                                    Text::new(label),
+                                    Text::default(),
                                     TextFont {
                                         font_size: 10.3,
                                         ..Default::default()
@@ -583,6 +951,22 @@ fn setup(
         },
     ));
 
+    // Pattern editor, filled in by set_pattern_editor
+
+    commands.spawn((
+        EditorPanel,
+        Node {
+            position_type: PositionType::Absolute,
+            display: Display::None,
+            flex_direction: FlexDirection::Column,
+            justify_self: JustifySelf::Center,
+            top: Val::Px(24.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.05, 0.05, 0.05, 0.9)),
+    ));
+
     // UI Buttons
 
     let mut node = commands.spawn(Node {
@@ -599,14 +983,40 @@ fn setup(
         for button_kind in &[
             ButtonKind::BpmDown10,
             ButtonKind::BpmDown1,
+            ButtonKind::BpmDownTenth,
+            ButtonKind::BpmEntry,
+            ButtonKind::BpmUpTenth,
             ButtonKind::BpmUp1,
             ButtonKind::BpmUp10,
             ButtonKind::DivisionDown1,
             ButtonKind::DivisionUp1,
+            ButtonKind::BeatsPerBarDown1,
+            ButtonKind::BeatsPerBarUp1,
+            ButtonKind::Meter,
+            ButtonKind::Polyrhythm,
+            ButtonKind::Pattern,
+            ButtonKind::Editor,
+            ButtonKind::Swing,
+            ButtonKind::GapClick,
+            ButtonKind::Dropout,
+            ButtonKind::DropoutSeed,
+            ButtonKind::Fade,
+            ButtonKind::FadeBackUp,
+            ButtonKind::TapTempo,
+            ButtonKind::FreeRunning,
+            ButtonKind::Judgement,
+            ButtonKind::JudgementWindows,
+            ButtonKind::Session,
             ButtonKind::TapMute,
             ButtonKind::TickMute,
             ButtonKind::HideBarChart,
             ButtonKind::HideClock,
+            ButtonKind::KeyboardTaps,
+            ButtonKind::SpeedTrainer,
+            ButtonKind::SpeedTrainerInterval,
+            ButtonKind::CeilingFinder,
+            ButtonKind::AudioCalibrate,
+            ButtonKind::VisualCalibrate,
         ] {
             let kind = *button_kind;
             parent
@@ -645,165 +1055,752 @@ fn setup(
 fn tap(
     mut commands: Commands,
     audio_handles: Res<AudioHandles>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    game_pad: Query<&Gamepad>,
-    buttons: Res<ButtonInput<MouseButton>>,
-    touches: Res<Touches>,
-    last_tick: Res<LastTick>,
-    timer: Res<Time<Fixed>>,
+    tap_inputs: TapInputs,
+    mut beat_grid: ResMut<BeatGrid>,
+    mut bpm: ResMut<Bpm>,
+    mut tap_tempo: ResMut<TapTempo>,
+    mut free_running: ResMut<FreeRunning>,
     division: Res<Division>,
+    polyrhythm: Res<Polyrhythm>,
+    time_signature: Res<TimeSignature>,
     mut tap_deltas: ResMut<TapDeltas>,
-    mute: Res<Mute>,
+    mut tapped: EventWriter<Tapped>,
+    mut calibration: ResMut<Calibration>,
+    mut latency_offset: ResMut<LatencyOffset>,
+    click_modes: ClickModes,
+    text_entry: Res<TextEntry>,
 ) {
-    if keyboard_input.get_just_pressed().count() > 0
-        || buttons.get_just_pressed().count() > 0
-        || touches.any_just_pressed()
-        || game_pad
-            .iter()
-            .any(|game_pad| game_pad.get_just_pressed().count() > 0)
-    {
-        if !mute.tap_mute {
-            commands.spawn((
-                AudioPlayer::new(audio_handles.tap().clone()),
-                PlaybackSettings::DESPAWN,
-            ));
-        }
+    let presses = tap_inputs.presses();
+
+    if presses.is_empty() || text_entry.is_active() {
+        return;
+    }
 
-        let now = Instant::now();
-        let time_step = timer.timestep();
-        let time_step_div = time_step / division.0;
+    if !click_modes.mute.tap_mute && calibration.kind() != Some(CalibrationKind::Visual) {
+        commands.spawn((
+            AudioPlayer::new(audio_handles.tap().clone()),
+            PlaybackSettings::DESPAWN,
+        ));
+    }
 
-        let last_tick = last_tick.0;
-        let next_tick = last_tick + time_step;
+    for press in presses {
+        if calibration.kind().is_some() {
+            let position = beat_grid.position(press.time);
+            let delta = (position - position.round()) * beat_grid.period().as_secs_f64();
+            match calibration.record(press.time, delta) {
+                Some((CalibrationKind::Audio, offset)) => latency_offset.audio = offset,
+                Some((CalibrationKind::Visual, offset)) => latency_offset.visual = Some(offset),
+                None => {}
+            }
+            continue;
+        }
 
-        let from_last = (now - last_tick).as_secs_f64();
-        let from_next = (next_tick - now).as_secs_f64();
+        if free_running.enabled {
+            // Judged against the player's own tempo, shown at the top of the clock
+            if let Some(delta) = free_running.press(press.time) {
+                let revolution =
+                    beat_grid.period().as_secs_f64() * time_signature.cycle_beats() as f64;
+                tap_deltas.0.push_front(Delta {
+                    delta,
+                    division: 1,
+                    pulse: 0,
+                    theta: delta / revolution * 2.0 * std::f64::consts::PI,
+                    hand: None,
+                    audible: false,
+                    volume: 1.0,
+                    position: beat_grid.position(press.time),
+                    missed: false,
+                    time: press.time,
+                });
+            }
+            continue;
+        }
 
-        let delta_from_last = from_last % time_step_div.as_secs_f64();
-        let delta_from_next = from_next % time_step_div.as_secs_f64();
+        if tap_tempo.enabled {
+            if let Some(estimate) = tap_tempo.press(press.time) {
+                bpm.0 = (estimate as f32).clamp(MIN_BPM, MAX_BPM);
+                let period = from_bpm(bpm.0);
+                beat_grid.set_period(period, press.time);
+                // Keep the click on the presses
+                beat_grid.align(beat_grid::offset(press.time, -latency_offset.audio));
+            }
+            continue;
+        }
 
-        let (delta, division) = if delta_from_last < delta_from_next {
-            let division = (from_last / time_step_div.as_secs_f64()) as usize;
-            (delta_from_last, division)
-        } else {
-            let division = (division.0 as usize
-                - (from_next / time_step_div.as_secs_f64()) as usize)
-                % division.0 as usize;
-            (-delta_from_next, division)
+        let now = beat_grid::offset(press.time, -latency_offset.audio);
+        let position = beat_grid.position(now);
+        // Each hand follows its own grid in a polyrhythm
+        let hand = polyrhythm.divisions().map(|_| press.hand);
+        let division = polyrhythm.division(press.hand).unwrap_or(division.0);
+        let (onset, beats) = time_signature.nearest_onset(position, division);
+        // The beat of the onset, which may differ from the beat of the press
+        let beat = (position - beats + 1e-6).floor() as i64;
+        let volume = click_modes.tick_volume(&time_signature, beat);
+
+        let delta = Delta {
+            delta: beats * beat_grid.period().as_secs_f64(),
+            division: onset.division as usize,
+            pulse: onset.pulse,
+            theta: time_signature.cycle_phase(position) * 2.0 * std::f64::consts::PI,
+            hand,
+            audible: volume > 0.0,
+            volume,
+            position: position - beats,
+            missed: false,
+            time: press.time,
         };
+        tapped.send(Tapped(delta.clone()));
+        tap_deltas.0.push_front(delta);
+    }
+    while tap_deltas.0.len() > BINS {
+        tap_deltas.0.pop_back();
+    }
+}
 
-        tap_deltas.0.push_front(Delta {
-            delta,
-            division,
-            theta: from_last / time_step.as_secs_f64() * 2.0 * std::f64::consts::PI,
-        });
-        while tap_deltas.0.len() > BINS {
-            tap_deltas.0.pop_back();
+fn load_click_sounds(
+    mut events: EventReader<AssetEvent<AudioSource>>,
+    audio_sources: Res<Assets<AudioSource>>,
+    audio_handles: Res<AudioHandles>,
+    click_track: Res<ClickTrack>,
+) {
+    for event in events.read() {
+        let AssetEvent::LoadedWithDependencies { id } = event else {
+            continue;
+        };
+        let Some(source) = audio_sources.get(*id) else {
+            continue;
+        };
+        for (index, handle) in audio_handles.handles.iter().enumerate() {
+            if handle.id() == *id {
+                click_track.set_sound(index, source);
+            }
         }
     }
 }
 
+fn sync_beat_grid(bpm: Res<Bpm>, mut beat_grid: ResMut<BeatGrid>) {
+    let period = from_bpm(bpm.0);
+    if beat_grid.period() != period {
+        beat_grid.set_period(period, Instant::now());
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn metronome(
-    mut commands: Commands,
     audio_handles: Res<AudioHandles>,
-    mut last_tick: ResMut<LastTick>,
-    mute: Res<Mute>,
+    click_track: Res<ClickTrack>,
+    beat_grid: Res<BeatGrid>,
+    mut schedule: ResMut<MetronomeSchedule>,
+    time_signature: Res<TimeSignature>,
+    division: Res<Division>,
+    polyrhythm: Res<Polyrhythm>,
+    click_modes: ClickModes,
+    calibration: Res<Calibration>,
+    free_running: Res<FreeRunning>,
 ) {
-    if !mute.tick_mute {
-        commands.spawn((
-            AudioPlayer::new(audio_handles.tick().clone()),
-            PlaybackSettings::DESPAWN,
-        ));
+    let now = Instant::now();
+
+    if beat_grid.is_changed()
+        || time_signature.is_changed()
+        || division.is_changed()
+        || polyrhythm.is_changed()
+        || click_modes.is_changed()
+        || calibration.is_changed()
+        || free_running.is_changed()
+    {
+        // Requeue everything the audio thread hasn't mixed yet on the new grid
+        click_track.cancel_pending();
+        schedule.next_beat = None;
+    }
+
+    // Clicks queued before the first render would be placed with a stale epoch
+    if !click_track.is_playing() {
+        return;
+    }
+
+    let mut next_beat = schedule.next_beat.unwrap_or_else(|| {
+        let mixed = click_track.frame_to_instant(click_track.rendered_frame());
+        beat_grid.beat_at_or_after(mixed.max(now))
+    });
+
+    while beat_grid.beat_time(next_beat) <= now + SCHEDULE_AHEAD {
+        let (_, bar_beat) = time_signature.bar_beat(next_beat);
+        let tick_volume = match calibration.kind() {
+            Some(CalibrationKind::Audio) => 1.0,
+            Some(CalibrationKind::Visual) => 0.0,
+            None if free_running.enabled => 0.0,
+            None => click_modes.tick_volume(&time_signature, next_beat),
+        };
+        let audible = tick_volume > 0.0;
+        let accent = time_signature.accent(bar_beat);
+        // A pattern note with a sound of its own clicks instead of the beat
+        let beat_click = accent
+            .click(&audio_handles)
+            .filter(|_| !note_sounds_on(&time_signature, &polyrhythm, bar_beat as f64));
+        if let (true, Some((sound, volume))) = (audible, beat_click) {
+            click_track.schedule(ScheduledClick {
+                frame: click_track.instant_to_frame(beat_grid.beat_time(next_beat)),
+                sound,
+                volume: volume * tick_volume,
+            });
+        }
+
+        if audible {
+            for (offset, (sound, volume)) in subdivision_clicks(
+                &time_signature,
+                &polyrhythm,
+                division.0,
+                &audio_handles,
+                bar_beat as f64,
+            ) {
+                let time = beat_grid::offset(
+                    beat_grid.beat_time(next_beat),
+                    offset * beat_grid.period().as_secs_f64(),
+                );
+                click_track.schedule(ScheduledClick {
+                    frame: click_track.instant_to_frame(time),
+                    sound,
+                    volume: volume * tick_volume,
+                });
+            }
+        }
+        next_beat += 1;
     }
-    last_tick.0 = Instant::now();
+    schedule.next_beat = Some(next_beat);
 }
 
-fn control(
-    mut timer: ResMut<Time<Fixed>>,
-    mut division: ResMut<Division>,
-    mut mute: ResMut<Mute>,
-    mut hide_clock: ResMut<HideClock>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+// Everything that decides which clicks the metronome plays, and how loud
+#[derive(SystemParam)]
+struct ClickModes<'w> {
+    mute: Res<'w, Mute>,
+    gap_click: Res<'w, GapClick>,
+    dropout: Res<'w, ClickDropout>,
+    fade: Res<'w, ClickFade>,
+}
+
+impl ClickModes<'_> {
+    fn is_changed(&self) -> bool {
+        self.mute.is_changed()
+            || self.gap_click.is_changed()
+            || self.dropout.is_changed()
+            || self.fade.is_changed()
+    }
+
+    // Volume factor of the metronome on `beat`, 0 when it doesn't tick
+    fn tick_volume(&self, time_signature: &TimeSignature, beat: i64) -> f32 {
+        let (bar, _) = time_signature.bar_beat(beat);
+        if self.mute.tick_mute || self.gap_click.is_silent(bar) || self.dropout.is_dropped(beat) {
+            0.0
+        } else {
+            self.fade.volume(beat, time_signature.beats_per_bar())
+        }
+    }
+}
+
+// Whether a note of the pattern with a sound plays at `bar_beat`
+fn note_sounds_on(time_signature: &TimeSignature, polyrhythm: &Polyrhythm, bar_beat: f64) -> bool {
+    polyrhythm.divisions().is_none()
+        && time_signature.pattern().is_some_and(|notes| {
+            notes
+                .iter()
+                .any(|note| note.sound.is_some() && (note.position - bar_beat).abs() < 1e-6)
+        })
+}
+
+// Clicks of the beat `bar_beat` beats into the bar, as (beats after the beat,
+// (sound, volume)). The grids of a polyrhythm click between the pulses, and so
+// do a pattern or a swung division so their feel can be heard.
+fn subdivision_clicks(
+    time_signature: &TimeSignature,
+    polyrhythm: &Polyrhythm,
+    division: u32,
+    audio_handles: &AudioHandles,
+    bar_beat: f64,
+) -> Vec<(f64, (usize, f32))> {
+    let weak = AccentLevel::Weak.click(audio_handles);
+    // The beat itself already clicked
+    let between = |position: f64| {
+        let offset = position - bar_beat;
+        (offset > 0.0 && offset < 1.0).then_some(offset)
+    };
+
+    if let Some(divisions) = polyrhythm.divisions() {
+        divisions
+            .into_iter()
+            .zip(audio_handles.hands)
+            .flat_map(|(division, sound)| {
+                time_signature
+                    .onsets(division)
+                    .into_iter()
+                    .filter_map(move |onset| Some((between(onset.position)?, (sound, 1.0))))
+            })
+            .collect()
+    } else if let Some(notes) = time_signature.pattern() {
+        // Notes with a sound of their own play on the beat too
+        notes
+            .iter()
+            .filter_map(|note| match note.sound {
+                Some(sound) => {
+                    let offset = note.position - bar_beat;
+                    (0.0..1.0)
+                        .contains(&offset)
+                        .then_some((offset, (sound, 1.0)))
+                }
+                None => Some((between(note.position)?, weak?)),
+            })
+            .collect()
+    } else if time_signature.is_swung() {
+        time_signature
+            .onsets(division)
+            .into_iter()
+            .filter_map(|onset| Some((between(onset.position)?, weak?)))
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+type ButtonInteractions<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Interaction, &'static ButtonKind),
+    (Changed<Interaction>, With<Button>),
+>;
+
+// Buttons clicked and shortcut keys pressed this frame, keys are ignored while typing
+// and while every key taps
+#[derive(SystemParam)]
+struct Controls<'w, 's> {
+    interaction_query: ButtonInteractions<'w, 's>,
+    keyboard_input: Res<'w, ButtonInput<KeyCode>>,
+    text_entry: ResMut<'w, TextEntry>,
+    keyboard_taps: ResMut<'w, KeyboardTaps>,
+}
+
+impl Controls<'_, '_> {
+    fn clicked(&self, kind: ButtonKind) -> bool {
+        self.interaction_query
+            .iter()
+            .any(|(interaction, button_kind)| {
+                *interaction == Interaction::Pressed && *button_kind == kind
+            })
+    }
+
+    fn key(&self, key: KeyCode) -> bool {
+        !self.text_entry.is_active()
+            && !self.keyboard_taps.0
+            && self.keyboard_input.just_pressed(key)
+    }
+
+    // Whether `kind` was clicked or its shortcut `key` pressed
+    fn pressed(&self, kind: ButtonKind, key: KeyCode) -> bool {
+        self.clicked(kind) || self.key(key)
+    }
+
+    // Shift steps the tempo by a tenth
+    fn fine(&self) -> bool {
+        self.keyboard_input
+            .any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn tempo_control(
+    mut controls: Controls,
+    mut bpm: ResMut<Bpm>,
+    mut tap_tempo: ResMut<TapTempo>,
+    mut free_running: ResMut<FreeRunning>,
+    mut tap_deltas: ResMut<TapDeltas>,
+    mut totals: ResMut<Totals>,
+    mut speed_trainer: ResMut<SpeedTrainer>,
+    mut ceiling_finder: ResMut<CeilingFinder>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::ArrowUp) {
-        let next_bpm = bpm(&timer).round() as u32 + 1;
-        timer.set_timestep(from_bpm(next_bpm as f32));
+    let fine = controls.fine();
+
+    if controls.clicked(ButtonKind::BpmUpTenth) || (fine && controls.key(KeyCode::ArrowUp)) {
+        bpm.step(0.1);
+    }
+
+    if controls.clicked(ButtonKind::BpmDownTenth) || (fine && controls.key(KeyCode::ArrowDown)) {
+        bpm.step(-0.1);
+    }
+
+    if controls.clicked(ButtonKind::BpmUp1) || (!fine && controls.key(KeyCode::ArrowUp)) {
+        bpm.step(1.0);
+    }
+
+    if controls.clicked(ButtonKind::BpmDown1) || (!fine && controls.key(KeyCode::ArrowDown)) {
+        bpm.step(-1.0);
+    }
+
+    if controls.pressed(ButtonKind::BpmUp10, KeyCode::ArrowRight) {
+        bpm.step(10.0);
     }
 
-    if keyboard_input.just_pressed(KeyCode::ArrowRight) {
-        let next_bpm = bpm(&timer).round() as u32 + 10;
-        timer.set_timestep(from_bpm(next_bpm as f32));
+    if controls.pressed(ButtonKind::BpmDown10, KeyCode::ArrowLeft) {
+        bpm.step(-10.0);
     }
 
-    if keyboard_input.just_pressed(KeyCode::ArrowDown) {
-        let current_bpm = bpm(&timer).round() as u32;
+    if controls.pressed(ButtonKind::BpmEntry, KeyCode::Enter) {
+        controls.text_entry.start(TextTarget::Bpm, String::new());
+    }
 
-        if current_bpm > 1 {
-            let next_bpm = current_bpm - 1;
-            timer.set_timestep(from_bpm(next_bpm as f32));
+    // Both take over the presses, so only one is used at a time
+    if controls.pressed(ButtonKind::TapTempo, KeyCode::KeyB) {
+        tap_tempo.toggle();
+        if tap_tempo.enabled && free_running.enabled {
+            free_running.toggle();
         }
     }
 
-    if keyboard_input.just_pressed(KeyCode::ArrowLeft) {
-        let current_bpm = bpm(&timer).round() as u32;
+    if controls.pressed(ButtonKind::FreeRunning, KeyCode::KeyR) {
+        free_running.toggle();
+        if free_running.enabled && tap_tempo.enabled {
+            tap_tempo.toggle();
+        }
+        // Deltas against the grid and against the player's tempo don't mix
+        tap_deltas.0.clear();
+        totals.reset();
+    }
 
-        let next_bpm = if current_bpm > 10 {
-            current_bpm - 10
-        } else {
-            1
-        };
+    if controls.pressed(ButtonKind::SpeedTrainer, KeyCode::KeyT) {
+        toggle_speed_trainer(&mut speed_trainer, &mut ceiling_finder, &mut bpm);
+    }
+
+    if controls.pressed(ButtonKind::SpeedTrainerInterval, KeyCode::KeyY) {
+        speed_trainer.toggle_interval();
+    }
+
+    if controls.pressed(ButtonKind::CeilingFinder, KeyCode::KeyF) {
+        toggle_ceiling_finder(&mut speed_trainer, &mut ceiling_finder, &mut bpm);
+    }
+}
+
+// The speed trainer and the ceiling finder both drive the tempo, so only one runs at a time
+fn toggle_speed_trainer(
+    speed_trainer: &mut SpeedTrainer,
+    ceiling_finder: &mut CeilingFinder,
+    bpm: &mut Bpm,
+) {
+    if ceiling_finder.is_running() {
+        ceiling_finder.toggle(bpm);
+    }
+    speed_trainer.toggle();
+}
 
-        timer.set_timestep(from_bpm(next_bpm as f32));
+fn toggle_ceiling_finder(
+    speed_trainer: &mut SpeedTrainer,
+    ceiling_finder: &mut CeilingFinder,
+    bpm: &mut Bpm,
+) {
+    if speed_trainer.enabled {
+        speed_trainer.toggle();
     }
+    ceiling_finder.toggle(bpm);
+}
 
-    if keyboard_input.just_pressed(KeyCode::BracketLeft) && division.0 > 1 {
+// The grid the player follows
+fn meter_control(
+    controls: Controls,
+    mut division: ResMut<Division>,
+    mut time_signature: ResMut<TimeSignature>,
+    mut polyrhythm: ResMut<Polyrhythm>,
+    mut patterns: ResMut<Patterns>,
+    beat_grid: Res<BeatGrid>,
+    mut editor: ResMut<PatternEditor>,
+) {
+    if controls.pressed(ButtonKind::DivisionDown1, KeyCode::BracketLeft) && division.0 > 1 {
         division.0 -= 1;
     }
 
-    if keyboard_input.just_pressed(KeyCode::BracketRight) {
+    if controls.pressed(ButtonKind::DivisionUp1, KeyCode::BracketRight) {
         division.0 += 1;
     }
 
-    if keyboard_input.just_pressed(KeyCode::KeyN) {
+    let next_beat = beat_grid.beat_before(Instant::now()) + 1;
+
+    if controls.pressed(ButtonKind::BeatsPerBarDown1, KeyCode::Minus)
+        && time_signature.beats_per_bar() > 1
+    {
+        let beats_per_bar = time_signature.beats_per_bar() - 1;
+        time_signature.set_beats_per_bar(beats_per_bar, next_beat);
+    }
+
+    if controls.pressed(ButtonKind::BeatsPerBarUp1, KeyCode::Equal) {
+        let beats_per_bar = time_signature.beats_per_bar() + 1;
+        time_signature.set_beats_per_bar(beats_per_bar, next_beat);
+    }
+
+    if controls.pressed(ButtonKind::Meter, KeyCode::KeyG) {
+        time_signature.next_grouping(next_beat);
+    }
+
+    if controls.pressed(ButtonKind::Polyrhythm, KeyCode::KeyP) {
+        next_polyrhythm(&mut polyrhythm, &mut patterns, &mut time_signature);
+    }
+
+    if controls.pressed(ButtonKind::Pattern, KeyCode::KeyO) {
+        next_pattern(&mut polyrhythm, &mut patterns, &mut time_signature);
+    }
+
+    if controls.pressed(ButtonKind::Editor, KeyCode::KeyE) {
+        editor.toggle(&patterns);
+    }
+
+    if controls.pressed(ButtonKind::Swing, KeyCode::KeyS) {
+        time_signature.next_swing();
+    }
+
+    // The accent buttons are handled by accent_button_system
+    for (i, key) in [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+        KeyCode::Digit8,
+        KeyCode::Digit9,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        if controls.key(key) && i < time_signature.accents.len() {
+            time_signature.accents[i] = time_signature.accents[i].next();
+        }
+    }
+}
+
+// Modes that change which clicks the metronome plays
+fn click_control(
+    mut controls: Controls,
+    beat_grid: Res<BeatGrid>,
+    time_signature: Res<TimeSignature>,
+    mut mute: ResMut<Mute>,
+    mut gap_click: ResMut<GapClick>,
+    mut dropout: ResMut<ClickDropout>,
+    mut fade: ResMut<ClickFade>,
+) {
+    if controls.pressed(ButtonKind::TapMute, KeyCode::KeyN) {
         mute.tap_mute = !mute.tap_mute;
     }
 
-    if keyboard_input.just_pressed(KeyCode::KeyM) {
+    if controls.pressed(ButtonKind::TickMute, KeyCode::KeyM) {
         mute.tick_mute = !mute.tick_mute;
     }
 
-    if keyboard_input.just_pressed(KeyCode::Comma) {
+    if controls.pressed(ButtonKind::GapClick, KeyCode::KeyK) {
+        gap_click.next();
+    }
+
+    if controls.pressed(ButtonKind::Dropout, KeyCode::KeyJ) {
+        dropout.next();
+    }
+
+    if controls.pressed(ButtonKind::DropoutSeed, KeyCode::KeyU) {
+        controls
+            .text_entry
+            .start(TextTarget::DropoutSeed, String::new());
+    }
+
+    if controls.pressed(ButtonKind::Fade, KeyCode::KeyL) {
+        // Fade from the next bar
+        let (bar, _) = time_signature.bar_beat(beat_grid.beat_before(Instant::now()));
+        fade.next(time_signature.bar_start(bar + 1));
+    }
+
+    if controls.pressed(ButtonKind::FadeBackUp, KeyCode::KeyI) {
+        fade.back_up = !fade.back_up;
+    }
+}
+
+fn judgement_control(
+    mut controls: Controls,
+    mut judgement: ResMut<Judgement>,
+    mut session: ResMut<Session>,
+    mut totals: ResMut<Totals>,
+    mut calibration: ResMut<Calibration>,
+) {
+    if controls.pressed(ButtonKind::Judgement, KeyCode::KeyH) {
+        judgement.next();
+    }
+
+    if controls.pressed(ButtonKind::JudgementWindows, KeyCode::KeyW) {
+        let windows = judgement.windows_text();
+        controls
+            .text_entry
+            .start(TextTarget::JudgementWindows, windows);
+    }
+
+    if controls.pressed(ButtonKind::Session, KeyCode::KeyA) {
+        session.toggle();
+        if session.is_running() {
+            totals.reset();
+        }
+    }
+
+    if controls.pressed(ButtonKind::AudioCalibrate, KeyCode::KeyC) {
+        calibration.start(CalibrationKind::Audio);
+    }
+
+    if controls.pressed(ButtonKind::VisualCalibrate, KeyCode::KeyV) {
+        calibration.start(CalibrationKind::Visual);
+    }
+}
+
+fn view_control(
+    mut controls: Controls,
+    mut hide_bar_chart: ResMut<HideBarChart>,
+    mut hide_clock: ResMut<HideClock>,
+) {
+    if controls.clicked(ButtonKind::HideBarChart) {
+        hide_bar_chart.0 = !hide_bar_chart.0;
+    }
+
+    if controls.pressed(ButtonKind::HideClock, KeyCode::Comma) {
         hide_clock.0 = !hide_clock.0;
     }
+
+    // Its key works with every key tapping too, it's the way back to the shortcuts
+    if controls.clicked(ButtonKind::KeyboardTaps)
+        || (!controls.text_entry.is_active()
+            && controls.keyboard_input.just_pressed(KEYBOARD_TAPS_KEY))
+    {
+        controls.keyboard_taps.0 = !controls.keyboard_taps.0;
+    }
 }
 
+// Sets the typed tempo, anything that isn't a tempo is ignored
+fn bpm_entry(mut entered: EventReader<TextEntered>, mut bpm: ResMut<Bpm>) {
+    for TextEntered(target, text) in entered.read() {
+        if *target != TextTarget::Bpm {
+            continue;
+        }
+        if let Some(typed) = Bpm::parse(text) {
+            bpm.0 = typed;
+        }
+    }
+}
+
+// A pattern replaces the grids of a polyrhythm, so only one is used at a time
+fn next_polyrhythm(
+    polyrhythm: &mut Polyrhythm,
+    patterns: &mut Patterns,
+    time_signature: &mut TimeSignature,
+) {
+    polyrhythm.next();
+    if polyrhythm.divisions().is_some() && patterns.selected().is_some() {
+        patterns.select(None, time_signature);
+    }
+}
+
+fn next_pattern(
+    polyrhythm: &mut Polyrhythm,
+    patterns: &mut Patterns,
+    time_signature: &mut TimeSignature,
+) {
+    patterns.next(time_signature);
+    if patterns.selected().is_some() {
+        polyrhythm.stop();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn set_status_text(
-    timer: Res<Time<Fixed>>,
+    bpm: Res<Bpm>,
     division: Res<Division>,
-    mute: Res<Mute>,
+    polyrhythm: Res<Polyrhythm>,
+    patterns: Res<Patterns>,
+    click_modes: ClickModes,
+    text_entry: Res<TextEntry>,
+    keyboard_taps: Res<KeyboardTaps>,
+    tap_tempo: Res<TapTempo>,
+    speed_trainer: Res<SpeedTrainer>,
+    ceiling_finder: Res<CeilingFinder>,
+    beat_grid: Res<BeatGrid>,
+    time_signature: Res<TimeSignature>,
+    mut bar_beat: Local<(i64, u32)>,
     mut query: Query<&mut Text, With<StatusText>>,
 ) {
-    if timer.is_changed() || division.is_changed() || mute.is_changed() {
+    let current = time_signature.bar_beat(beat_grid.beat_before(Instant::now()));
+
+    if current != *bar_beat
+        || bpm.is_changed()
+        || division.is_changed()
+        || polyrhythm.is_changed()
+        || patterns.is_changed()
+        || time_signature.is_changed()
+        || click_modes.is_changed()
+        || text_entry.is_changed()
+        || keyboard_taps.is_changed()
+        || tap_tempo.is_changed()
+        || speed_trainer.is_changed()
+        || ceiling_finder.is_changed()
+    {
+        *bar_beat = current;
+
+        let bpm_label = if text_entry.target() == Some(TextTarget::Bpm) {
+            format!("{}_", text_entry.text)
+        } else {
+            bpm.label()
+        };
+        let dropout_label = if text_entry.target() == Some(TextTarget::DropoutSeed) {
+            format!("seed {}_", text_entry.text)
+        } else {
+            click_modes.dropout.label()
+        };
+
         for mut text in &mut query {
             text.0 = format!(
-                "BPM: {}\n1 / {}\nTick Mute: {}\nTap Mute: {}",
-                bpm(&timer).round() as u32,
+                "BPM: {}\nBar: {} Beat: {} / {}\nMeter: {}\n1 / {}\nPolyrhythm: {}\nPattern: {}\nSwing: {}\nTick Mute: {}\nTap Mute: {}\nGap Click: {}\nDropout: {}\nFade: {}\nTap Tempo: {}\nSpeed Trainer: {}\nCeiling: {}\nKeys tap: {}",
+                bpm_label,
+                current.0 + 1,
+                current.1 + 1,
+                time_signature.beats_per_bar(),
+                time_signature.label(),
                 division.0,
-                mute.tick_mute,
-                mute.tap_mute
+                polyrhythm.label(),
+                patterns.label(),
+                time_signature.swing_label(),
+                click_modes.mute.tick_mute,
+                click_modes.mute.tap_mute,
+                click_modes.gap_click.label(),
+                dropout_label,
+                click_modes.fade.label(
+                    beat_grid.beat_before(Instant::now()),
+                    time_signature.beats_per_bar()
+                ),
+                tap_tempo.label(),
+                speed_trainer.label(),
+                ceiling_finder.label(&bpm),
+                keyboard_taps.label()
             );
         }
     }
 }
 
 fn clock(
-    last_tick: Res<LastTick>,
-    timer: Res<Time<Fixed>>,
+    beat_grid: Res<BeatGrid>,
+    time_signature: Res<TimeSignature>,
+    latency_offset: Res<LatencyOffset>,
+    calibration: Res<Calibration>,
+    free_running: Res<FreeRunning>,
     mut query: Query<&mut Transform, With<ClockMarker>>,
 ) {
-    let now = Instant::now();
-    let time_step = timer.timestep();
-    let delta = (now - last_tick.0).as_secs_f64() / time_step.as_secs_f64();
+    // The visual calibration measures against the unshifted marker
+    let shift = if calibration.kind() == Some(CalibrationKind::Visual) {
+        0.0
+    } else {
+        latency_offset.marker_shift()
+    };
+    let now = beat_grid::offset(Instant::now(), shift);
+    // There is no beat to follow without the metronome
+    let delta = if free_running.enabled {
+        0.0
+    } else {
+        time_signature.cycle_phase(beat_grid.position(now))
+    };
 
     let angle = 2.0 * std::f32::consts::PI * delta as f32;
 
@@ -829,12 +1826,17 @@ fn set_bins(
 ) {
     if tap_deltas.is_changed() {
         for (BinIndex(index), mut node, mut color, mut visibility) in &mut query_bar {
-            if let Some(Delta { delta, .. }) = tap_deltas.0.get(*index) {
+            if let Some(Delta { delta, missed, .. }) = tap_deltas.0.get(*index) {
                 let height = delta.abs() as f32 * BAR_HEIGHT_MULTIPLIER;
                 node.height = Val::Px(height);
                 node.position_type = PositionType::Absolute;
 
-                if *delta >= 0.0 {
+                if *missed {
+                    // Up to the end of the late window
+                    color.0 = Color::linear_rgba(0.5, 0.5, 0.5, 0.6);
+                    node.top = Val::DEFAULT;
+                    node.bottom = Val::Percent(50.0);
+                } else if *delta >= 0.0 {
                     color.0 = Color::linear_rgba(1.0, 0.0, 0.0, 0.6);
                     node.top = Val::DEFAULT;
                     node.bottom = Val::Percent(50.0);
@@ -852,10 +1854,27 @@ fn set_bins(
 
         for (BinIndex(index), mut text) in &mut query_text {
             if let Some(Delta {
-                delta, division, ..
+                delta,
+                division,
+                hand,
+                volume,
+                missed,
+                ..
             }) = tap_deltas.0.get(*index)
             {
-                text.0 = format!("[{}]{:+.1}", division, delta * 1000.0);
+                let hand = hand.as_ref().map_or("", |hand| hand.label());
+                // Shows where timing drifts as the click fades
+                let volume = if *volume < 1.0 {
+                    format!("\n{:.0}%", volume * 100.0)
+                } else {
+                    String::new()
+                };
+                let delta = if *missed {
+                    "miss".to_string()
+                } else {
+                    format!("{:+.1}", delta * 1000.0)
+                };
+                text.0 = format!("[{}{}]{}{}", hand, division, delta, volume);
             } else {
                 text.0 = "".to_string();
             }
@@ -863,6 +1882,39 @@ fn set_bins(
     }
 }
 
+// Line at the edge of a judgement window, `None` for the line at 0
+#[derive(Component, Clone, Copy)]
+struct GuideLine {
+    tier: Option<Tier>,
+    border: f32,
+}
+
+#[derive(Component)]
+struct GuideLabel(Option<Tier>);
+
+fn set_guide_lines(
+    judgement: Res<Judgement>,
+    mut lines: Query<(&GuideLine, &mut Node)>,
+    mut labels: Query<(&GuideLabel, &mut Text)>,
+) {
+    if !judgement.is_changed() {
+        return;
+    }
+
+    let window = |tier: Option<Tier>| tier.and_then(|tier| judgement.window(tier)).unwrap_or(0.0);
+
+    for (line, mut node) in &mut lines {
+        node.height = Val::Px(window(line.tier) as f32 * BAR_HEIGHT_MULTIPLIER + line.border / 2.0);
+    }
+
+    for (GuideLabel(tier), mut text) in &mut labels {
+        text.0 = match tier {
+            Some(tier) => format!("{} {:.1}", tier.label(), window(Some(*tier)) * 1000.0),
+            None => "0".to_string(),
+        };
+    }
+}
+
 fn hide_bar_chart(
     mut bar_chart: Query<&mut Visibility, With<BarChart>>,
     hide_bar_chart: Res<HideBarChart>,
@@ -893,66 +1945,103 @@ fn hide_clock(mut clock: Query<&mut Visibility, With<Clock>>, hide_clock: Res<Hi
 #[derive(Component)]
 struct ClockLegend;
 
+// The right hand's grid of a polyrhythm is drawn inside the left one
+fn ring_radius(hand: Option<Hand>) -> f32 {
+    match hand {
+        Some(Hand::Right) => CIRCLE_SIZE * 0.75,
+        _ => CIRCLE_SIZE,
+    }
+}
+
+// Point `phase` of a revolution clockwise from the top of the clock
+fn clock_point(phase: f32, radius: f32) -> Vec2 {
+    let angle = 2.0 * std::f32::consts::PI * phase;
+    Vec2::new(angle.sin() * radius, angle.cos() * radius)
+}
+
+// Tick pointing out from the centre at `phase` of a revolution, clockwise like the legend
+fn precision_tick(phase: f32, radius: f32, length: f32) -> Transform {
+    let mut transform = Transform::from_scale(Vec3::new(6.0, length, 1.0))
+        .with_translation(Vec3::new(0.0, radius, 8.0));
+    let theta = phase * 2.0 * std::f32::consts::PI;
+    transform.rotate_around(Vec3::ZERO, Quat::from_rotation_z(-theta));
+    transform
+}
+
+#[allow(clippy::too_many_arguments)]
 fn set_clock_legend(
     mut commands: Commands,
     query: Query<Entity, With<ClockLegend>>,
     parent: Query<Entity, With<Clock>>,
     division: Res<Division>,
+    polyrhythm: Res<Polyrhythm>,
     clock_resource: Res<ClockResource>,
-    timer: Res<Time<Fixed>>,
+    beat_grid: Res<BeatGrid>,
+    time_signature: Res<TimeSignature>,
+    judgement: Res<Judgement>,
 ) {
-    if division.is_changed() || timer.is_changed() {
+    if division.is_changed()
+        || polyrhythm.is_changed()
+        || beat_grid.is_changed()
+        || time_signature.is_changed()
+        || judgement.is_changed()
+    {
         for e in query.iter() {
             commands.entity(e).despawn_recursive();
         }
 
-        let division = division.0;
-        let tick = timer.timestep().as_secs_f32();
+        let rings = match polyrhythm.divisions() {
+            Some(divisions) => [Hand::Left, Hand::Right]
+                .map(|hand| {
+                    (
+                        divisions[hand.index()],
+                        Some(hand),
+                        clock_resource.material_hands[hand.index()].clone(),
+                    )
+                })
+                .to_vec(),
+            None => vec![(division.0, None, clock_resource.material_legend.clone())],
+        };
+        // Duration of one revolution
+        let tick = beat_grid.period().as_secs_f32() * time_signature.cycle_beats() as f32;
 
         for parent in &parent {
             commands.entity(parent).with_children(|commands| {
-                for i in 0..division {
-                    let angle = 2.0 * std::f32::consts::PI * (i as f32 / division as f32);
-                    let x = angle.sin() * CIRCLE_SIZE;
-                    let y = angle.cos() * CIRCLE_SIZE;
-
-                    commands.spawn((
-                        ClockLegend,
-                        Mesh2d(clock_resource.mesh_legend.clone()),
-                        MeshMaterial2d(clock_resource.material_legend.clone()),
-                        Transform::from_xyz(x, y, 3.0),
-                    ));
-
-                    let t = tick / division as f32 * i as f32;
-
-                    for delta in [
-                        -1.0 / 60.0,
-                        1.0 / 60.0,
-                        -1.5 / 60.0,
-                        1.5 / 60.0,
-                        -2.0 / 60.0,
-                        2.0 / 60.0,
-                    ] {
-                        let theta = (t + delta) / tick * 2.0 * std::f32::consts::PI;
+                for (division, hand, material) in &rings {
+                    let radius = ring_radius(*hand);
+                    let onsets = time_signature.cycle_onsets(*division);
 
-                        let mut transform = Transform::from_scale(Vec3::new(
-                            6.0,
-                            96.0 * (-delta.abs() * 60.0).exp(),
-                            1.0,
-                        ))
-                        .with_translation(Vec3::new(
-                            0.0,
-                            CIRCLE_SIZE,
-                            8.0,
-                        ));
-                        transform.rotate_around(Vec3::ZERO, Quat::from_rotation_z(theta));
+                    for onset in onsets.iter().map(|onset| *onset as f32) {
+                        let point = clock_point(onset, radius);
 
                         commands.spawn((
                             ClockLegend,
-                            Mesh2d(clock_resource.mesh_precision.clone()),
-                            MeshMaterial2d(clock_resource.material_precision.clone()),
-                            transform,
+                            Mesh2d(clock_resource.mesh_legend.clone()),
+                            MeshMaterial2d(material.clone()),
+                            Transform::from_xyz(point.x, point.y, 3.0),
                         ));
+
+                        let t = tick * onset;
+
+                        // Both edges of each judgement window, shorter for the wider ones
+                        let edges = Tier::ALL.iter().enumerate().flat_map(|(i, tier)| {
+                            let window = judgement.window(*tier).map(|window| window as f32);
+                            window
+                                .into_iter()
+                                .flat_map(move |window| [(i, -window), (i, window)])
+                        });
+                        for (i, delta) in edges {
+                            commands.spawn((
+                                ClockLegend,
+                                Mesh2d(clock_resource.mesh_precision.clone()),
+                                MeshMaterial2d(clock_resource.material_precision.clone()),
+                                precision_tick(
+                                    (t + delta) / tick,
+                                    radius,
+                                    96.0 * (-1.0 - 0.5 * i as f32).exp(),
+                                ),
+                            ));
+                        }
                     }
                 }
             });
@@ -977,9 +2066,11 @@ fn set_clock_delta(
 
         for parent in &parent {
             commands.entity(parent).with_children(|commands| {
-                for Delta { theta, .. } in tap_deltas.0.iter() {
-                    let x = theta.sin() as f32 * CIRCLE_SIZE;
-                    let y = theta.cos() as f32 * CIRCLE_SIZE;
+                // A miss has no tap to draw
+                for Delta { theta, hand, .. } in tap_deltas.0.iter().filter(|d| !d.missed) {
+                    let radius = ring_radius(*hand);
+                    let x = theta.sin() as f32 * radius;
+                    let y = theta.cos() as f32 * radius;
 
                     commands.spawn((
                         ClockDelta,
@@ -993,6 +2084,36 @@ fn set_clock_delta(
     }
 }
 
+// Points from the centre of the clock at the mean tap angle, the beat is at the top
+#[derive(Component)]
+struct BiasNeedle;
+
+fn set_bias_needle(
+    totals: Res<Totals>,
+    beat_grid: Res<BeatGrid>,
+    time_signature: Res<TimeSignature>,
+    mut query: Query<(&mut Transform, &mut Visibility), With<BiasNeedle>>,
+) {
+    if !(totals.is_changed() || beat_grid.is_changed() || time_signature.is_changed()) {
+        return;
+    }
+
+    // On the same scale as the precision legend
+    let revolution = beat_grid.period().as_secs_f64() * time_signature.cycle_beats() as f64;
+    let bias = totals.bias();
+
+    for (mut transform, mut visibility) in &mut query {
+        let Some(bias) = bias else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        let theta = (bias / revolution * 2.0 * std::f64::consts::PI) as f32;
+        *transform = Transform::from_xyz(0.0, CIRCLE_SIZE / 2.0, 2.0);
+        transform.rotate_around(Vec3::ZERO, Quat::from_rotation_z(-theta));
+        *visibility = Visibility::Inherited;
+    }
+}
+
 #[derive(Component)]
 struct DiagnosticsText;
 
@@ -1029,79 +2150,19 @@ fn diagnostics_text_update_system(
     }
 }
 
+// Highlights the pressed buttons, what they do is handled by the *_control systems
 #[allow(clippy::type_complexity)]
 fn button_system(
     mut interaction_query: Query<
-        (
-            &Interaction,
-            &mut BackgroundColor,
-            &mut BorderColor,
-            &ButtonKind,
-        ),
-        (Changed<Interaction>, With<Button>),
+        (&Interaction, &mut BackgroundColor, &mut BorderColor),
+        (Changed<Interaction>, With<Button>, With<ButtonKind>),
     >,
-
-    mut timer: ResMut<Time<Fixed>>,
-    mut division: ResMut<Division>,
-    mut mute: ResMut<Mute>,
-    mut hide_bar_chart: ResMut<HideBarChart>,
-    mut hide_clock: ResMut<HideClock>,
 ) {
-    for (interaction, mut color, mut border_color, button_kind) in &mut interaction_query {
+    for (interaction, mut color, mut border_color) in &mut interaction_query {
         match *interaction {
             Interaction::Pressed => {
                 *color = PRESSED_BUTTON.into();
                 border_color.0 = RED.into();
-
-                match button_kind {
-                    ButtonKind::BpmUp1 => {
-                        let next_bpm = bpm(&timer).round() as u32 + 1;
-                        timer.set_timestep(from_bpm(next_bpm as f32));
-                    }
-                    ButtonKind::BpmDown1 => {
-                        let current_bpm = bpm(&timer).round() as u32;
-
-                        if current_bpm > 1 {
-                            let next_bpm = current_bpm - 1;
-                            timer.set_timestep(from_bpm(next_bpm as f32));
-                        }
-                    }
-                    ButtonKind::BpmUp10 => {
-                        let next_bpm = bpm(&timer).round() as u32 + 10;
-                        timer.set_timestep(from_bpm(next_bpm as f32));
-                    }
-                    ButtonKind::BpmDown10 => {
-                        let current_bpm = bpm(&timer).round() as u32;
-
-                        let next_bpm = if current_bpm > 10 {
-                            current_bpm - 10
-                        } else {
-                            1
-                        };
-
-                        timer.set_timestep(from_bpm(next_bpm as f32));
-                    }
-                    ButtonKind::DivisionUp1 => {
-                        division.0 += 1;
-                    }
-                    ButtonKind::DivisionDown1 => {
-                        if division.0 > 1 {
-                            division.0 -= 1;
-                        }
-                    }
-                    ButtonKind::TapMute => {
-                        mute.tap_mute = !mute.tap_mute;
-                    }
-                    ButtonKind::TickMute => {
-                        mute.tick_mute = !mute.tick_mute;
-                    }
-                    ButtonKind::HideBarChart => {
-                        hide_bar_chart.0 = !hide_bar_chart.0;
-                    }
-                    ButtonKind::HideClock => {
-                        hide_clock.0 = !hide_clock.0;
-                    }
-                }
             }
             Interaction::None | Interaction::Hovered => {
                 *color = NORMAL_BUTTON.into();
@@ -1125,6 +2186,9 @@ fn set_audio_indices(
                 Index::Tap => {
                     format!("tap: {}", audio_handles.tap)
                 }
+                Index::Accent => {
+                    format!("accent: {}", audio_handles.accent)
+                }
             };
         }
     }
@@ -1155,16 +2219,234 @@ fn index_button_system(
                     audio_handles.tap = (audio_handles.tap + audio_handles.handles.len() - 1)
                         % audio_handles.handles.len();
                 }
+                IndexButton::AccentIncrement => {
+                    audio_handles.accent = (audio_handles.accent + 1) % audio_handles.handles.len();
+                }
+                IndexButton::AccentDecrement => {
+                    audio_handles.accent = (audio_handles.accent + audio_handles.handles.len() - 1)
+                        % audio_handles.handles.len();
+                }
             },
             Interaction::None | Interaction::Hovered => {}
         }
     }
 }
 
-fn set_statistics(tap_deltas: Res<TapDeltas>, mut query: Query<&mut Text, With<Statistics>>) {
-    let mean = tap_deltas.0.iter().map(|d| d.delta.abs()).sum::<f64>() / tap_deltas.0.len() as f64;
+#[allow(clippy::too_many_arguments)]
+fn set_statistics(
+    tap_deltas: Res<TapDeltas>,
+    latency_offset: Res<LatencyOffset>,
+    calibration: Res<Calibration>,
+    time_signature: Res<TimeSignature>,
+    polyrhythm: Res<Polyrhythm>,
+    gap_click: Res<GapClick>,
+    dropout: Res<ClickDropout>,
+    free_running: Res<FreeRunning>,
+    judgement: Res<Judgement>,
+    text_entry: Res<TextEntry>,
+    combo: Res<Combo>,
+    session: Res<Session>,
+    totals: Res<Totals>,
+    mut query: Query<&mut Text, With<Statistics>>,
+) {
+    // Misses carry the late window rather than a tap's delta
+    let taps = tap_deltas.0.iter().filter(|d| !d.missed);
+    let mean = taps.clone().map(|d| d.delta.abs()).sum::<f64>() / taps.clone().count() as f64;
+
+    let mean_of = |label: String, filter: &dyn Fn(&Delta) -> bool| {
+        let deltas = taps.clone().filter(|d| filter(d));
+        let count = deltas.clone().count();
+        if count == 0 {
+            format!("{}: -", label)
+        } else {
+            let mean = deltas.map(|d| d.delta.abs()).sum::<f64>() / count as f64;
+            format!("{}: {:.1}", label, mean * 1000.0)
+        }
+    };
+
+    let bias = totals
+        .bias()
+        .map_or("-".to_string(), |bias| format!("{:+.1}", bias * 1000.0));
+
+    let per_beat = (0..time_signature.pulses() as u32)
+        .map(|beat| mean_of((beat + 1).to_string(), &|d| d.pulse == beat))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let per_hand = if polyrhythm.divisions().is_some() {
+        let per_hand = [Hand::Left, Hand::Right]
+            .map(|hand| mean_of(hand.label().to_string(), &|d| d.hand == Some(hand)))
+            .join(" ");
+        format!("\nper hand: {}", per_hand)
+    } else {
+        String::new()
+    };
+
+    let per_click = if gap_click.is_enabled() || dropout.is_enabled() {
+        format!(
+            "\n{} {}",
+            mean_of("audible".to_string(), &|d| d.audible),
+            mean_of("silent".to_string(), &|d| !d.audible)
+        )
+    } else {
+        String::new()
+    };
+
+    // Without a grid, the player's tempo stability replaces the per beat means
+    let breakdown = if free_running.enabled {
+        let ms = |secs: f64| format!("{:.1}", secs * 1000.0);
+        format!(
+            "free running: {}\nIOI variance(ms^2): {} sd(ms): {}\ndrift(BPM/min): {}",
+            free_running.label(),
+            free_running
+                .variance()
+                .map_or("-".to_string(), |v| format!("{:.1}", v * 1e6)),
+            free_running
+                .variance()
+                .map_or("-".to_string(), |v| ms(v.sqrt())),
+            free_running
+                .drift()
+                .map_or("-".to_string(), |drift| format!("{:+.1}", drift))
+        )
+    } else {
+        format!("per beat: {}{}{}", per_beat, per_hand, per_click)
+    };
+
+    let tiers = if text_entry.target() == Some(TextTarget::JudgementWindows) {
+        format!("windows(ms): {}_", text_entry.text)
+    } else {
+        let counts = Tier::ALL
+            .map(|tier| format!("{} {}", tier.label(), totals.count(tier)))
+            .join(" ");
+        format!(
+            "{}: {} ({} untapped)\ncombo: {} best: {}\nsession: {}",
+            judgement.label(),
+            counts,
+            totals.untapped,
+            combo.count,
+            combo.best,
+            session.label()
+        )
+    };
+
+    let offset = if let (Some(kind), Some(taps)) = (calibration.kind(), calibration.progress()) {
+        format!(
+            "Calibrating {}: {}/{}",
+            kind.label(),
+            taps,
+            CALIBRATION_TAPS
+        )
+    } else {
+        let visual = latency_offset
+            .visual
+            .map_or("-".to_string(), |visual| format!("{:+.1}", visual * 1000.0));
+        format!(
+            "audio offset(ms): {:+.1}\nvisual offset(ms): {}",
+            latency_offset.audio * 1000.0,
+            visual
+        )
+    };
 
     for mut text in &mut query {
-        text.0 = format!("|avg(ms)|: {:.1}", mean * 1000.0);
+        text.0 = format!(
+            "|avg(ms)|: {:.1}\nFAST: {} SLOW: {} bias(ms): {}\n{}\n{}\n{}",
+            mean * 1000.0,
+            totals.fast,
+            totals.slow,
+            bias,
+            breakdown,
+            tiers,
+            offset
+        );
+    }
+}
+
+#[derive(Component)]
+struct AccentButtons;
+
+#[derive(Component)]
+struct AccentButton(usize);
+
+fn set_accent_buttons(
+    mut commands: Commands,
+    time_signature: Res<TimeSignature>,
+    container: Query<Entity, With<AccentButtons>>,
+    buttons: Query<Entity, With<AccentButton>>,
+) {
+    if time_signature.is_changed() {
+        for e in buttons.iter() {
+            commands.entity(e).despawn_recursive();
+        }
+
+        for container in &container {
+            commands.entity(container).with_children(|commands| {
+                for (i, accent) in time_signature.accents.iter().enumerate() {
+                    commands
+                        .spawn((
+                            Button,
+                            AccentButton(i),
+                            Node {
+                                border: UiRect::all(Val::Px(2.0)),
+                                padding: UiRect::horizontal(Val::Px(4.0)),
+                                margin: UiRect {
+                                    right: Val::Px(2.0),
+                                    ..Default::default()
+                                },
+                                ..default()
+                            },
+                            BorderColor(Color::BLACK),
+                            BackgroundColor(NORMAL_BUTTON),
+                        ))
+                        .with_child(Text::new(format!("{}{}", i + 1, accent.label())));
+                }
+            });
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn accent_button_system(
+    interaction_query: Query<(&Interaction, &AccentButton), (Changed<Interaction>, With<Button>)>,
+    mut time_signature: ResMut<TimeSignature>,
+) {
+    for (interaction, AccentButton(i)) in &interaction_query {
+        if *interaction == Interaction::Pressed && *i < time_signature.accents.len() {
+            time_signature.accents[*i] = time_signature.accents[*i].next();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn out_of_range_bpm_entry_is_rejected() {
+        assert_eq!(Bpm::parse(" 174.5 "), Some(174.5));
+        assert_eq!(Bpm::parse("1000"), Some(MAX_BPM));
+        assert_eq!(Bpm::parse("1e9"), None);
+        assert_eq!(Bpm::parse("1000.1"), None);
+        assert_eq!(Bpm::parse("0.5"), None);
+        assert_eq!(Bpm::parse("inf"), None);
+        assert_eq!(Bpm::parse("NaN"), None);
+
+        let mut bpm = Bpm(995.0);
+        bpm.step(10.0);
+        assert_eq!(bpm.0, MAX_BPM);
+    }
+
+    #[test]
+    fn precision_tick_lands_on_its_onset() {
+        let mut time_signature = TimeSignature::default();
+        time_signature.next_grouping(0);
+        assert_eq!(time_signature.label(), "2+2+3");
+
+        // The second pulse of 2+2+3 is 2/7 of the way round, its mirror 5/7 isn't a pulse
+        let phase = time_signature.cycle_onsets(1)[1] as f32;
+        assert!((phase - 2.0 / 7.0).abs() < 1e-6);
+
+        let tick = precision_tick(phase, CIRCLE_SIZE, 10.0);
+        let point = clock_point(phase, CIRCLE_SIZE);
+        assert!(tick.translation.truncate().distance(point) < 1e-3);
+        assert!(tick.translation.x > 0.0);
     }
 }
@@ -0,0 +1,350 @@
+use bevy::prelude::*;
+
+use crate::{AudioHandles, pattern::Note};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AccentLevel {
+    Strong,
+    Normal,
+    Weak,
+    Silent,
+}
+
+impl AccentLevel {
+    pub fn label(&self) -> &str {
+        match self {
+            AccentLevel::Strong => "S",
+            AccentLevel::Normal => "N",
+            AccentLevel::Weak => "W",
+            AccentLevel::Silent => "-",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            AccentLevel::Strong => AccentLevel::Normal,
+            AccentLevel::Normal => AccentLevel::Weak,
+            AccentLevel::Weak => AccentLevel::Silent,
+            AccentLevel::Silent => AccentLevel::Strong,
+        }
+    }
+
+    /// Sound index and volume of the click.
+    pub fn click(&self, audio_handles: &AudioHandles) -> Option<(usize, f32)> {
+        match self {
+            AccentLevel::Strong => Some((audio_handles.accent, 1.0)),
+            AccentLevel::Normal => Some((audio_handles.tick, 1.0)),
+            AccentLevel::Weak => Some((audio_handles.tick, 0.4)),
+            AccentLevel::Silent => None,
+        }
+    }
+}
+
+/// Groupings cycled through by the meter button, empty means a simple meter.
+pub const GROUPINGS: &[&[u32]] = &[
+    &[],
+    &[2, 2, 3],
+    &[3, 2, 2],
+    &[2, 3],
+    &[3, 2],
+    &[3, 3],
+    &[3, 3, 2],
+    &[2, 2, 2, 3],
+];
+
+/// Swing ratios cycled through by the swing button, as the share of a pair of
+/// subdivisions taken by the first one. 0.5 is straight, 2/3 is triplet swing.
+pub const SWINGS: &[f64] = &[0.5, 0.6, 2.0 / 3.0, 0.75];
+
+/// A point of the bar where a tap is expected.
+#[derive(Clone, Copy)]
+pub struct Onset {
+    /// Beats of the grid from the start of the bar.
+    pub position: f64,
+    /// Index of the pulse (group) in the bar.
+    pub pulse: u32,
+    /// Index of the subdivision in the pulse.
+    pub division: u32,
+}
+
+/// The bar as groups of grid beats, e.g. 7/8 counted as 2+2+3 eighths.
+///
+/// The first beat of every group is a pulse of the meter. A simple meter has
+/// groups of one beat, so every beat is a pulse.
+#[derive(Resource)]
+pub struct TimeSignature {
+    groups: Vec<u32>,
+    // Bars start at `origin_beat` of the beat grid
+    origin_beat: i64,
+    swing: f64,
+    // Notes of a custom rhythm, replacing the division grid
+    pattern: Option<Vec<Note>>,
+    /// One per beat of the bar.
+    pub accents: Vec<AccentLevel>,
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        let groups = vec![1; 4];
+        Self {
+            accents: default_accents(&groups),
+            groups,
+            origin_beat: 0,
+            swing: SWINGS[0],
+            pattern: None,
+        }
+    }
+}
+
+fn default_accents(groups: &[u32]) -> Vec<AccentLevel> {
+    let mut accents = Vec::new();
+    for group in groups {
+        accents.push(AccentLevel::Normal);
+        accents.extend((1..*group).map(|_| AccentLevel::Weak));
+    }
+    accents[0] = AccentLevel::Strong;
+    accents
+}
+
+impl TimeSignature {
+    pub fn beats_per_bar(&self) -> u32 {
+        self.groups.iter().sum()
+    }
+
+    pub fn pulses(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_grouped(&self) -> bool {
+        self.groups.iter().any(|group| *group > 1)
+    }
+
+    pub fn label(&self) -> String {
+        if self.is_grouped() {
+            self.groups
+                .iter()
+                .map(|group| group.to_string())
+                .collect::<Vec<_>>()
+                .join("+")
+        } else {
+            format!("{} beats", self.beats_per_bar())
+        }
+    }
+
+    /// Beats shown by one revolution of the clock. Uneven pulses and patterns only
+    /// line up over a whole bar.
+    pub fn cycle_beats(&self) -> u32 {
+        if self.is_grouped() || self.pattern.is_some() {
+            self.beats_per_bar()
+        } else {
+            1
+        }
+    }
+
+    /// (bar, beat in bar), both counted from 0.
+    pub fn bar_beat(&self, beat: i64) -> (i64, u32) {
+        let beats = beat - self.origin_beat;
+        let n = self.beats_per_bar() as i64;
+        (beats.div_euclid(n), beats.rem_euclid(n) as u32)
+    }
+
+    /// The pulse a beat of the bar belongs to.
+    pub fn pulse(&self, bar_beat: u32) -> u32 {
+        let mut start = 0;
+        for (pulse, group) in self.groups.iter().enumerate() {
+            start += group;
+            if bar_beat < start {
+                return pulse as u32;
+            }
+        }
+        self.groups.len() as u32 - 1
+    }
+
+    pub fn bar_start(&self, bar: i64) -> i64 {
+        self.origin_beat + bar * self.beats_per_bar() as i64
+    }
+
+    /// Beats from the start of the bar at `position` of the grid.
+    pub fn in_bar(&self, position: f64) -> f64 {
+        let (bar, _) = self.bar_beat(position.floor() as i64);
+        position - self.bar_start(bar) as f64
+    }
+
+    /// Fraction of a clock revolution at `position` of the grid.
+    pub fn cycle_phase(&self, position: f64) -> f64 {
+        let cycle = self.cycle_beats() as f64;
+        self.in_bar(position).rem_euclid(cycle) / cycle
+    }
+
+    /// The onset nearest to `position` of the grid and the distance to it in beats,
+    /// positive when late.
+    pub fn nearest_onset(&self, position: f64, division: u32) -> (Onset, f64) {
+        let in_bar = self.in_bar(position);
+        let beats_per_bar = self.beats_per_bar() as f64;
+
+        // The nearest onset may be in the previous or next bar
+        self.onsets(division)
+            .into_iter()
+            .flat_map(|onset| {
+                [-1.0, 0.0, 1.0].map(|bars| (onset, in_bar - onset.position - bars * beats_per_bar))
+            })
+            .min_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+            .expect("a bar has at least one onset")
+    }
+
+    pub fn accent(&self, bar_beat: u32) -> AccentLevel {
+        self.accents[bar_beat as usize]
+    }
+
+    /// The notes of the pattern that fit in the bar, otherwise every pulse split
+    /// into `division` parts, even unless swung.
+    pub fn onsets(&self, division: u32) -> Vec<Onset> {
+        if let Some(onsets) = self.pattern_onsets() {
+            return onsets;
+        }
+
+        let mut onsets = Vec::new();
+        let mut start = 0;
+        for (pulse, group) in self.groups.iter().enumerate() {
+            for i in 0..division {
+                onsets.push(Onset {
+                    position: start as f64 + *group as f64 * self.fraction(i, division),
+                    pulse: pulse as u32,
+                    division: i,
+                });
+            }
+            start += group;
+        }
+        onsets
+    }
+
+    fn pattern_onsets(&self) -> Option<Vec<Onset>> {
+        let beats_per_bar = self.beats_per_bar() as f64;
+        let mut onsets: Vec<Onset> = Vec::new();
+        for Note { position, .. } in self.pattern.as_ref()? {
+            // Notes of several sounds may share a position
+            if !(0.0..beats_per_bar).contains(position)
+                || onsets.last().is_some_and(|last| last.position == *position)
+            {
+                continue;
+            }
+            let pulse = self.pulse(position.floor() as u32);
+            // Notes are numbered within their pulse, like the subdivisions of a grid
+            let division = onsets.iter().filter(|onset| onset.pulse == pulse).count() as u32;
+            onsets.push(Onset {
+                position: *position,
+                pulse,
+                division,
+            });
+        }
+        (!onsets.is_empty()).then_some(onsets)
+    }
+
+    pub fn pattern(&self) -> Option<&[Note]> {
+        self.pattern.as_deref()
+    }
+
+    /// Expects taps only on `notes`, sorted by position.
+    pub fn set_pattern(&mut self, notes: Option<Vec<Note>>) {
+        self.pattern = notes;
+    }
+
+    // Where subdivision `i` falls in its pulse. Swing delays the second of every
+    // pair, so only even divisions swing.
+    fn fraction(&self, i: u32, division: u32) -> f64 {
+        if !division.is_multiple_of(2) {
+            return i as f64 / division as f64;
+        }
+        let pair = (i - i % 2) as f64;
+        let offbeat = if i % 2 == 1 { 2.0 * self.swing } else { 0.0 };
+        (pair + offbeat) / division as f64
+    }
+
+    pub fn is_swung(&self) -> bool {
+        self.swing != SWINGS[0]
+    }
+
+    pub fn swing_label(&self) -> String {
+        if self.is_swung() {
+            format!("{:.0}%", self.swing * 100.0)
+        } else {
+            "straight".to_string()
+        }
+    }
+
+    /// Switches to the next of [`SWINGS`].
+    pub fn next_swing(&mut self) {
+        let current = SWINGS
+            .iter()
+            .position(|swing| *swing == self.swing)
+            .unwrap_or(0);
+        self.swing = SWINGS[(current + 1) % SWINGS.len()];
+    }
+
+    /// Positions of the onsets shown on the clock, in fractions of a revolution.
+    pub fn cycle_onsets(&self, division: u32) -> Vec<f64> {
+        let cycle = self.cycle_beats() as f64;
+        self.onsets(division)
+            .into_iter()
+            .map(|onset| onset.position)
+            .filter(|position| *position < cycle)
+            .map(|position| position / cycle)
+            .collect()
+    }
+
+    /// A simple meter, the new bar starts at `next_beat`.
+    pub fn set_beats_per_bar(&mut self, beats_per_bar: u32, next_beat: i64) {
+        if self.is_grouped() {
+            self.accents = default_accents(&vec![1; beats_per_bar as usize]);
+        } else {
+            self.accents
+                .resize(beats_per_bar as usize, AccentLevel::Normal);
+        }
+        self.groups = vec![1; beats_per_bar as usize];
+        self.origin_beat = next_beat;
+    }
+
+    /// Switches to the next of [`GROUPINGS`], the new bar starts at `next_beat`.
+    pub fn next_grouping(&mut self, next_beat: i64) {
+        let current = GROUPINGS
+            .iter()
+            .position(|grouping| self.is_grouped() && *grouping == self.groups.as_slice())
+            .unwrap_or(0);
+        let grouping = GROUPINGS[(current + 1) % GROUPINGS.len()];
+
+        self.groups = if grouping.is_empty() {
+            vec![1; 4]
+        } else {
+            grouping.to_vec()
+        };
+        self.accents = default_accents(&self.groups);
+        self.origin_beat = next_beat;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_onset_may_be_in_the_next_or_previous_bar() {
+        let mut time_signature = TimeSignature::default();
+        time_signature.next_grouping(0);
+        assert_eq!(time_signature.label(), "2+2+3");
+
+        let (onset, off) = time_signature.nearest_onset(2.8, 1);
+        assert_eq!((onset.pulse, onset.division), (1, 0));
+        assert!((off - 0.8).abs() < 1e-6);
+
+        // Around the barline the onset is taken from whichever bar is closer
+        let (onset, off) = time_signature.nearest_onset(6.9, 2);
+        assert_eq!((onset.pulse, onset.division), (0, 0));
+        assert!((off + 0.1).abs() < 1e-6);
+        let (onset, off) = time_signature.nearest_onset(7.2, 2);
+        assert_eq!((onset.pulse, onset.division), (0, 0));
+        assert!((off - 0.2).abs() < 1e-6);
+        let (onset, off) = time_signature.nearest_onset(-1.2, 2);
+        assert_eq!((onset.pulse, onset.division), (2, 1));
+        assert!((off - 0.3).abs() < 1e-6);
+    }
+}
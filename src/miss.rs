@@ -0,0 +1,290 @@
+use bevy::prelude::*;
+use bevy::utils::Instant;
+
+use crate::{
+    BINS, ClickModes, Delta, Division, Missed, Polyrhythm, TapDeltas, Tapped, TimeSignature,
+    beat_grid::{self, BeatGrid},
+    calibration::{Calibration, LatencyOffset},
+    free_running::FreeRunning,
+    input_time::Hand,
+    judgement::{Judgement, Tier},
+    popup::Combo,
+    tap_tempo::TapTempo,
+};
+
+/// Bars without a tap after which the player is taken to have stopped, and
+/// the onsets after them aren't counted as missed.
+const STOP_AFTER_BARS: f64 = 2.0;
+
+/// Records the onsets of the grid that weren't tapped.
+#[derive(Resource, Default)]
+pub struct MissDetector {
+    /// Onsets up to this position of the grid have been checked.
+    checked: Option<f64>,
+    /// The onsets tapped since, with the grid of the hand that tapped them.
+    tapped: Vec<(Option<Hand>, f64)>,
+    last_tap: Option<f64>,
+}
+
+/// Missed onsets a late tap can still replace, with the grid of their hand.
+#[derive(Default)]
+pub struct Untapped(Vec<(Option<Hand>, f64)>);
+
+impl Untapped {
+    pub fn push(&mut self, missed: &Delta) {
+        self.0.push((missed.hand, missed.position));
+    }
+
+    /// Removes the miss a tap too late for its window is for, returns whether
+    /// there was one.
+    pub fn replace(&mut self, tap: &Delta) -> bool {
+        let Some(i) = self.0.iter().position(|(hand, position)| {
+            *hand == tap.hand && (position - tap.position).abs() < 1e-6
+        }) else {
+            return false;
+        };
+        self.0.swap_remove(i);
+        true
+    }
+
+    /// Forgets the misses no later tap can be for, taps are matched to the
+    /// nearest onset and never one a beat behind.
+    pub fn forget_before(&mut self, tap: &Delta) {
+        self.0
+            .retain(|(_, position)| *position > tap.position - 1.0);
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = f64> + '_ {
+        self.0.iter().map(|(_, position)| *position)
+    }
+}
+
+impl MissDetector {
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn is_tapped(&self, hand: Option<Hand>, position: f64) -> bool {
+        self.tapped
+            .iter()
+            .any(|(h, p)| *h == hand && (p - position).abs() < 1e-6)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn detect_misses(
+    mut detector: ResMut<MissDetector>,
+    mut tapped: EventReader<Tapped>,
+    mut missed_events: EventWriter<Missed>,
+    mut tap_deltas: ResMut<TapDeltas>,
+    mut combo: ResMut<Combo>,
+    beat_grid: Res<BeatGrid>,
+    time_signature: Res<TimeSignature>,
+    division: Res<Division>,
+    polyrhythm: Res<Polyrhythm>,
+    judgement: Res<Judgement>,
+    latency_offset: Res<LatencyOffset>,
+    calibration: Res<Calibration>,
+    free_running: Res<FreeRunning>,
+    tap_tempo: Res<TapTempo>,
+    click_modes: ClickModes,
+) {
+    // Nothing is expected without a grid to follow
+    if calibration.kind().is_some() || free_running.enabled || tap_tempo.enabled {
+        tapped.clear();
+        detector.reset();
+        return;
+    }
+
+    let stop_after = STOP_AFTER_BARS * time_signature.beats_per_bar() as f64;
+    for Tapped(delta) in tapped.read() {
+        // Onsets before the first tap, or while the player had stopped, aren't missed
+        if detector
+            .last_tap
+            .is_none_or(|last| delta.position > last + stop_after)
+        {
+            detector.checked = Some(delta.position);
+        }
+        // A tap too late for its window replaces the miss already recorded
+        if detector
+            .checked
+            .is_some_and(|checked| delta.position <= checked)
+        {
+            tap_deltas.0.retain(|d| {
+                !(d.missed && d.hand == delta.hand && (d.position - delta.position).abs() < 1e-6)
+            });
+        }
+        detector.tapped.push((delta.hand, delta.position));
+        detector.last_tap = Some(
+            detector
+                .last_tap
+                .map_or(delta.position, |last| last.max(delta.position)),
+        );
+    }
+    let (Some(checked), Some(last_tap)) = (detector.checked, detector.last_tap) else {
+        return;
+    };
+
+    // An onset is missed once its late window has closed
+    let period = beat_grid.period().as_secs_f64();
+    let late = judgement.window(Tier::Bad).unwrap_or_default();
+    let now = beat_grid.position(beat_grid::offset(Instant::now(), -latency_offset.audio));
+    let until = (now - late / period).min(last_tap + stop_after);
+    if until <= checked {
+        return;
+    }
+
+    let grids = match polyrhythm.divisions() {
+        Some(divisions) => [Hand::Left, Hand::Right]
+            .map(|hand| (Some(hand), divisions[hand.index()]))
+            .to_vec(),
+        None => vec![(None, division.0)],
+    };
+    let (first_bar, _) = time_signature.bar_beat(checked.floor() as i64);
+    let (last_bar, _) = time_signature.bar_beat(until.floor() as i64);
+
+    let mut missed = Vec::new();
+    for (hand, division) in grids {
+        let onsets = time_signature.onsets(division);
+        for bar in first_bar..=last_bar {
+            let start = time_signature.bar_start(bar) as f64;
+            for onset in &onsets {
+                let position = start + onset.position;
+                if position <= checked || position > until || detector.is_tapped(hand, position) {
+                    continue;
+                }
+                let beat = (position + 1e-6).floor() as i64;
+                let volume = click_modes.tick_volume(&time_signature, beat);
+                let time = beat_grid::offset(
+                    beat_grid.beat_time(beat),
+                    (position - beat as f64) * period + latency_offset.audio,
+                );
+                missed.push(Delta {
+                    delta: late,
+                    division: onset.division as usize,
+                    pulse: onset.pulse,
+                    theta: time_signature.cycle_phase(position) * 2.0 * std::f64::consts::PI,
+                    hand,
+                    audible: volume > 0.0,
+                    volume,
+                    position,
+                    missed: true,
+                    time,
+                });
+            }
+        }
+    }
+
+    missed.sort_by(|a, b| a.position.total_cmp(&b.position));
+    for delta in missed {
+        combo.judge(Tier::Miss);
+        missed_events.send(Missed(delta.clone()));
+        tap_deltas.0.push_front(delta);
+    }
+    while tap_deltas.0.len() > BINS {
+        tap_deltas.0.pop_back();
+    }
+
+    detector.checked = Some(until);
+    detector.tapped.retain(|(_, position)| *position > until);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::utils::Duration;
+
+    use super::*;
+    use crate::{Mute, dropout::ClickDropout, fade::ClickFade, gap_click::GapClick};
+
+    // A 120 BPM grid in 4/4 that started a bit over 10 beats ago
+    fn world() -> World {
+        let mut world = World::new();
+        let period = Duration::from_millis(500);
+        world.insert_resource(BeatGrid::new(
+            beat_grid::offset(Instant::now(), -10.2 * period.as_secs_f64()),
+            period,
+        ));
+        world.insert_resource(TapDeltas(VecDeque::new()));
+        world.insert_resource(Division(1));
+        world.init_resource::<MissDetector>();
+        world.init_resource::<Combo>();
+        world.init_resource::<TimeSignature>();
+        world.init_resource::<Polyrhythm>();
+        world.init_resource::<Judgement>();
+        world.init_resource::<LatencyOffset>();
+        world.init_resource::<Calibration>();
+        world.init_resource::<FreeRunning>();
+        world.init_resource::<TapTempo>();
+        world.init_resource::<Mute>();
+        world.init_resource::<GapClick>();
+        world.init_resource::<ClickDropout>();
+        world.init_resource::<ClickFade>();
+        world.init_resource::<Events<Tapped>>();
+        world.init_resource::<Events<Missed>>();
+        world
+    }
+
+    fn detect(world: &mut World, taps: &[f64]) -> Vec<f64> {
+        for position in taps {
+            world.send_event(Tapped(Delta::tap(None, *position, 0.0)));
+        }
+        world.run_system_once(detect_misses).unwrap();
+        let mut missed = world.resource_mut::<Events<Missed>>();
+        let positions = missed
+            .iter_current_update_events()
+            .map(|Missed(delta)| delta.position)
+            .collect();
+        missed.clear();
+        positions
+    }
+
+    #[test]
+    fn onsets_after_the_first_tap_are_missed_until_the_player_stops() {
+        let mut world = world();
+        // Two bars after the last tap the player is taken to have stopped
+        assert_eq!(
+            detect(&mut world, &[0.0, 3.0]),
+            [1.0, 2.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0]
+        );
+        assert_eq!(world.resource::<TapDeltas>().0.len(), 9);
+        assert!(detect(&mut world, &[]).is_empty());
+    }
+
+    #[test]
+    fn no_onset_is_missed_before_the_first_tap() {
+        let mut world = world();
+        assert!(detect(&mut world, &[]).is_empty());
+        assert_eq!(detect(&mut world, &[9.0]), [10.0]);
+    }
+
+    #[test]
+    fn late_tap_replaces_the_recorded_miss() {
+        let mut world = world();
+        assert_eq!(detect(&mut world, &[0.0]).len(), 8);
+
+        // The tap also keeps the player going past the earlier stop
+        assert_eq!(detect(&mut world, &[4.0]), [9.0, 10.0]);
+        let tap_deltas = &world.resource::<TapDeltas>().0;
+        assert_eq!(tap_deltas.len(), 9);
+        assert!(tap_deltas.iter().all(|d| d.position != 4.0));
+    }
+
+    #[test]
+    fn untapped_onset_is_replaced_once_by_its_own_grid() {
+        let mut untapped = Untapped::default();
+        untapped.push(&Delta::miss(Some(Hand::Left), 2.0));
+        untapped.push(&Delta::miss(Some(Hand::Right), 2.0));
+
+        assert!(!untapped.replace(&Delta::tap(Some(Hand::Left), 2.5, 0.1)));
+        assert!(untapped.replace(&Delta::tap(Some(Hand::Left), 2.0, 0.1)));
+        assert!(!untapped.replace(&Delta::tap(Some(Hand::Left), 2.0, 0.1)));
+        assert_eq!(untapped.positions().collect::<Vec<_>>(), [2.0]);
+
+        // No later tap can be for an onset a beat behind
+        untapped.forget_before(&Delta::tap(Some(Hand::Left), 3.0, 0.0));
+        assert_eq!(untapped.positions().count(), 0);
+    }
+}
@@ -0,0 +1,326 @@
+use bevy::prelude::*;
+
+use crate::{TimeSignature, storage};
+
+// Key the user's patterns are saved under
+const STORAGE_KEY: &str = "patterns";
+
+/// A note of a [`Pattern`].
+#[derive(Clone, Copy, PartialEq)]
+pub struct Note {
+    /// Beats from the start of the bar.
+    pub position: f64,
+    /// Index into `AudioHandles`, the weak tick when `None`.
+    pub sound: Option<usize>,
+}
+
+/// A one-bar rhythm, taps are only expected on its notes.
+#[derive(Clone)]
+pub struct Pattern {
+    pub name: String,
+    /// Sorted by position. Notes past the end of a shorter bar are dropped.
+    pub notes: Vec<Note>,
+}
+
+impl Pattern {
+    fn new(name: &str, positions: &[f64]) -> Self {
+        Self {
+            name: name.to_string(),
+            notes: positions
+                .iter()
+                .map(|position| Note {
+                    position: *position,
+                    sound: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// `positions` of one beat, repeated over four.
+    fn per_beat(name: &str, positions: &[f64]) -> Self {
+        let positions = (0..4)
+            .flat_map(|beat| positions.iter().map(move |position| beat as f64 + position))
+            .collect::<Vec<_>>();
+        Self::new(name, &positions)
+    }
+
+    /// The notes on a grid of `steps` steps, as `rows[sound][step]`. Notes off the
+    /// grid snap to the nearest step, notes without a sound go on `default_sound`.
+    pub fn to_steps(
+        &self,
+        steps_per_beat: u32,
+        steps: usize,
+        sounds: usize,
+        default_sound: usize,
+    ) -> Vec<Vec<bool>> {
+        let mut rows = vec![vec![false; steps]; sounds];
+        for note in &self.notes {
+            let step = (note.position * steps_per_beat as f64).round() as usize;
+            let sound = note.sound.unwrap_or(default_sound);
+            if step < steps && sound < sounds {
+                rows[sound][step] = true;
+            }
+        }
+        rows
+    }
+
+    /// Adds a note of `sound` at `position`, or removes the notes already there.
+    /// Notes without a sound are on `default_sound`, like in [`Pattern::to_steps`].
+    pub fn toggle(&mut self, position: f64, sound: usize, default_sound: usize) {
+        let len = self.notes.len();
+        self.notes.retain(|note| {
+            (note.position - position).abs() >= 1e-6 || note.sound.unwrap_or(default_sound) != sound
+        });
+        if self.notes.len() == len {
+            let at = self.notes.partition_point(|note| note.position <= position);
+            self.notes.insert(
+                at,
+                Note {
+                    position,
+                    sound: Some(sound),
+                },
+            );
+        }
+    }
+
+    /// The coarsest of `choices` that has every note on a step.
+    pub fn steps_per_beat(&self, choices: &[u32]) -> Option<u32> {
+        let mut choices = choices.to_vec();
+        choices.sort();
+        choices.into_iter().find(|steps_per_beat| {
+            self.notes.iter().all(|note| {
+                let step = note.position * *steps_per_beat as f64;
+                (step - step.round()).abs() < 1e-6
+            })
+        })
+    }
+
+    // One line, the name then `position:sound` pairs, `-` for no sound
+    fn serialize(&self) -> String {
+        let notes = self
+            .notes
+            .iter()
+            .map(|note| {
+                let sound = note
+                    .sound
+                    .map_or("-".to_string(), |sound| sound.to_string());
+                format!("{}:{}", note.position, sound)
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        format!("{}\t{}", self.name, notes)
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let (name, notes) = line.split_once('\t')?;
+        let mut notes = notes
+            .split_whitespace()
+            .map(|note| {
+                let (position, sound) = note.split_once(':')?;
+                Some(Note {
+                    position: position.parse().ok()?,
+                    sound: match sound {
+                        "-" => None,
+                        sound => Some(sound.parse().ok()?),
+                    },
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        notes.sort_by(|a, b| a.position.total_cmp(&b.position));
+        Some(Self {
+            name: name.to_string(),
+            notes,
+        })
+    }
+}
+
+/// Patterns to pick from, and the one in use.
+#[derive(Resource)]
+pub struct Patterns {
+    pub patterns: Vec<Pattern>,
+    selected: Option<usize>,
+}
+
+impl Default for Patterns {
+    fn default() -> Self {
+        Self {
+            patterns: vec![
+                Pattern::per_beat("Gallop", &[0.0, 0.5, 0.75]),
+                Pattern::per_beat("Reverse gallop", &[0.0, 0.25, 0.5]),
+                Pattern::per_beat("Dotted", &[0.0, 0.75]),
+                Pattern::per_beat("Syncopated", &[0.0, 0.25, 0.75]),
+                Pattern::new(
+                    "Quarter triplets",
+                    &[0.0, 2.0 / 3.0, 4.0 / 3.0, 2.0, 8.0 / 3.0, 10.0 / 3.0],
+                ),
+                Pattern::new("Son clave", &[0.0, 0.75, 1.5, 2.5, 3.0]),
+            ],
+            selected: None,
+        }
+    }
+}
+
+impl Patterns {
+    /// The presets followed by the saved patterns, a saved pattern replaces a preset of the same name.
+    pub fn load() -> Self {
+        let mut patterns = Self::default();
+        for pattern in saved() {
+            patterns.insert(pattern);
+        }
+        patterns
+    }
+
+    // Replaces the pattern of the same name, returns its index
+    fn insert(&mut self, pattern: Pattern) -> usize {
+        match self.patterns.iter().position(|p| p.name == pattern.name) {
+            Some(i) => {
+                self.patterns[i] = pattern;
+                i
+            }
+            None => {
+                self.patterns.push(pattern);
+                self.patterns.len() - 1
+            }
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Pattern> {
+        self.selected.map(|i| &self.patterns[i])
+    }
+
+    /// Selects `selected` and applies it to `time_signature`.
+    pub fn select(&mut self, selected: Option<usize>, time_signature: &mut TimeSignature) {
+        self.selected = selected.filter(|i| *i < self.patterns.len());
+        time_signature.set_pattern(self.selected().map(|pattern| pattern.notes.clone()));
+    }
+
+    /// Switches to the next pattern, turning off after the last one.
+    pub fn next(&mut self, time_signature: &mut TimeSignature) {
+        let selected = match self.selected {
+            None => Some(0),
+            Some(i) => Some(i + 1),
+        };
+        self.select(selected, time_signature);
+    }
+
+    /// Switches to the previous pattern, turning off before the first one.
+    pub fn previous(&mut self, time_signature: &mut TimeSignature) {
+        let selected = match self.selected {
+            None => self.patterns.len().checked_sub(1),
+            Some(i) => i.checked_sub(1),
+        };
+        self.select(selected, time_signature);
+    }
+
+    /// Replaces the selected pattern, or adds it when none is selected, and applies it.
+    pub fn update(&mut self, pattern: Pattern, time_signature: &mut TimeSignature) {
+        let selected = match self.selected {
+            Some(i) => {
+                self.patterns[i] = pattern;
+                i
+            }
+            None => self.insert(pattern),
+        };
+        self.select(Some(selected), time_signature);
+    }
+
+    /// A name no pattern uses yet.
+    pub fn new_name(&self) -> String {
+        (1..)
+            .map(|i| format!("Pattern {}", i))
+            .find(|name| self.patterns.iter().all(|pattern| pattern.name != *name))
+            .expect("names run out after the patterns")
+    }
+
+    /// Saves the selected pattern under its name.
+    pub fn save_selected(&self) {
+        let Some(pattern) = self.selected() else {
+            return;
+        };
+
+        let mut saved = saved();
+        match saved.iter().position(|p| p.name == pattern.name) {
+            Some(i) => saved[i] = pattern.clone(),
+            None => saved.push(pattern.clone()),
+        }
+        write_saved(&saved);
+    }
+
+    /// Renames the selected pattern and its saved copy. Returns `false` without a
+    /// selected pattern, or when another pattern already has the name.
+    pub fn rename_selected(&mut self, name: &str) -> bool {
+        let Some(i) = self.selected else {
+            return false;
+        };
+        if self.patterns.iter().any(|pattern| pattern.name == name) {
+            return false;
+        }
+        let old = std::mem::replace(&mut self.patterns[i].name, name.to_string());
+
+        let mut saved = saved();
+        if let Some(pattern) = saved.iter_mut().find(|pattern| pattern.name == old) {
+            pattern.name = name.to_string();
+            write_saved(&saved);
+        }
+        true
+    }
+
+    pub fn label(&self) -> &str {
+        self.selected().map_or("off", |pattern| &pattern.name)
+    }
+}
+
+fn write_saved(saved: &[Pattern]) {
+    let lines = saved
+        .iter()
+        .map(Pattern::serialize)
+        .collect::<Vec<_>>()
+        .join("\n");
+    storage::save(STORAGE_KEY, &lines);
+}
+
+fn saved() -> Vec<Pattern> {
+    storage::load(STORAGE_KEY)
+        .map(|lines| lines.lines().filter_map(Pattern::parse).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rename_refuses_a_name_in_use() {
+        let mut patterns = Patterns::default();
+        let mut time_signature = TimeSignature::default();
+        patterns.select(Some(0), &mut time_signature);
+
+        assert!(!patterns.rename_selected("Dotted"));
+        assert_eq!(patterns.label(), "Gallop");
+        assert_eq!(
+            patterns
+                .patterns
+                .iter()
+                .filter(|pattern| pattern.name == "Dotted")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn toggle_keeps_the_notes_off_the_grid() {
+        let mut pattern = Pattern::new("Triplets", &[0.0, 1.0 / 3.0, 2.0 / 3.0]);
+        pattern.toggle(0.5, 2, 0);
+        pattern.toggle(0.0, 0, 0);
+
+        let notes = pattern
+            .notes
+            .iter()
+            .map(|note| (note.position, note.sound))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            notes,
+            [(1.0 / 3.0, None), (0.5, Some(2)), (2.0 / 3.0, None)]
+        );
+    }
+}
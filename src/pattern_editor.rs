@@ -0,0 +1,308 @@
+use bevy::prelude::*;
+
+use crate::{
+    AudioHandles, NORMAL_BUTTON, PRESSED_BUTTON, TimeSignature,
+    pattern::{Pattern, Patterns},
+    polyrhythm::Polyrhythm,
+    text_entry::{TextEntered, TextEntry, TextTarget},
+};
+
+/// Steps per beat the editor can show.
+const STEPS_PER_BEAT: &[u32] = &[2, 3, 4, 6, 8];
+
+const BEAT_START_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
+
+/// Step grid of the selected pattern, a row per sound and a column per step.
+#[derive(Resource)]
+pub struct PatternEditor {
+    pub open: bool,
+    steps_per_beat: u32,
+}
+
+impl Default for PatternEditor {
+    fn default() -> Self {
+        Self {
+            open: false,
+            steps_per_beat: 4,
+        }
+    }
+}
+
+impl PatternEditor {
+    pub fn toggle(&mut self, patterns: &Patterns) {
+        self.open = !self.open;
+        self.fit(patterns);
+    }
+
+    // Shows the selected pattern on a grid that has all of its notes
+    fn fit(&mut self, patterns: &Patterns) {
+        if let Some(steps_per_beat) = patterns
+            .selected()
+            .and_then(|pattern| pattern.steps_per_beat(STEPS_PER_BEAT))
+        {
+            self.steps_per_beat = steps_per_beat;
+        }
+    }
+
+    fn change_steps(&mut self, up: bool) {
+        let current = STEPS_PER_BEAT
+            .iter()
+            .position(|steps_per_beat| *steps_per_beat == self.steps_per_beat)
+            .unwrap_or(0);
+        let next = if up {
+            (current + 1).min(STEPS_PER_BEAT.len() - 1)
+        } else {
+            current.saturating_sub(1)
+        };
+        self.steps_per_beat = STEPS_PER_BEAT[next];
+    }
+}
+
+#[derive(Component)]
+pub struct EditorPanel;
+
+#[derive(Component)]
+pub struct EditorCell {
+    sound: usize,
+    step: usize,
+}
+
+#[derive(Component, Clone, Copy)]
+pub enum EditorButton {
+    Previous,
+    Next,
+    New,
+    Rename,
+    Save,
+    StepsDown,
+    StepsUp,
+}
+
+impl EditorButton {
+    fn label(&self) -> &str {
+        match self {
+            EditorButton::Previous => "<",
+            EditorButton::Next => ">",
+            EditorButton::New => "New",
+            EditorButton::Rename => "Rename",
+            EditorButton::Save => "Save",
+            EditorButton::StepsDown => "Steps-",
+            EditorButton::StepsUp => "Steps+",
+        }
+    }
+}
+
+pub fn set_pattern_editor(
+    mut commands: Commands,
+    editor: Res<PatternEditor>,
+    patterns: Res<Patterns>,
+    time_signature: Res<TimeSignature>,
+    audio_handles: Res<AudioHandles>,
+    text_entry: Res<TextEntry>,
+    mut panel: Query<(Entity, &mut Node), With<EditorPanel>>,
+) {
+    if !(editor.is_changed()
+        || patterns.is_changed()
+        || time_signature.is_changed()
+        || audio_handles.is_changed()
+        || text_entry.is_changed())
+    {
+        return;
+    }
+
+    let steps_per_beat = editor.steps_per_beat;
+    let steps = (time_signature.beats_per_bar() * steps_per_beat) as usize;
+    let sounds = audio_handles.handles.len();
+    let rows = patterns.selected().map_or_else(
+        || vec![vec![false; steps]; sounds],
+        |pattern| pattern.to_steps(steps_per_beat, steps, sounds, audio_handles.tick),
+    );
+    let name = if text_entry.target() == Some(TextTarget::PatternName) {
+        format!("{}_", text_entry.text)
+    } else {
+        patterns.label().to_string()
+    };
+
+    for (entity, mut node) in &mut panel {
+        node.display = if editor.open {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        commands.entity(entity).despawn_descendants();
+        if !editor.open {
+            continue;
+        }
+
+        commands.entity(entity).with_children(|commands| {
+            commands
+                .spawn(Node {
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    margin: UiRect::bottom(Val::Px(4.0)),
+                    ..default()
+                })
+                .with_children(|commands| {
+                    commands.spawn((
+                        Text::new(format!("{} ({}/beat)", name, steps_per_beat)),
+                        Node {
+                            margin: UiRect::right(Val::Px(8.0)),
+                            ..default()
+                        },
+                    ));
+                    for button in [
+                        EditorButton::Previous,
+                        EditorButton::Next,
+                        EditorButton::New,
+                        EditorButton::Rename,
+                        EditorButton::Save,
+                        EditorButton::StepsDown,
+                        EditorButton::StepsUp,
+                    ] {
+                        commands
+                            .spawn((
+                                Button,
+                                button,
+                                Node {
+                                    border: UiRect::all(Val::Px(2.0)),
+                                    padding: UiRect::horizontal(Val::Px(4.0)),
+                                    margin: UiRect::right(Val::Px(2.0)),
+                                    ..default()
+                                },
+                                BorderColor(Color::BLACK),
+                                BorderRadius::all(Val::Px(4.0)),
+                                BackgroundColor(NORMAL_BUTTON),
+                            ))
+                            .with_child(Text::new(button.label()));
+                    }
+                });
+
+            for (sound, row) in rows.iter().enumerate() {
+                commands
+                    .spawn(Node {
+                        display: Display::Flex,
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    })
+                    .with_children(|commands| {
+                        commands.spawn((
+                            Text::new(format!("{}", sound)),
+                            Node {
+                                width: Val::Px(24.0),
+                                ..default()
+                            },
+                        ));
+                        for (step, on) in row.iter().enumerate() {
+                            let color = if *on {
+                                PRESSED_BUTTON
+                            } else if step % steps_per_beat as usize == 0 {
+                                BEAT_START_BUTTON
+                            } else {
+                                NORMAL_BUTTON
+                            };
+                            commands.spawn((
+                                Button,
+                                EditorCell { sound, step },
+                                Node {
+                                    width: Val::Px(20.0),
+                                    height: Val::Px(20.0),
+                                    margin: UiRect::all(Val::Px(1.0)),
+                                    ..default()
+                                },
+                                BackgroundColor(color),
+                            ));
+                        }
+                    });
+            }
+        });
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub fn pattern_editor_buttons(
+    cells: Query<(&Interaction, &EditorCell), (Changed<Interaction>, With<Button>)>,
+    buttons: Query<(&Interaction, &EditorButton), (Changed<Interaction>, With<Button>)>,
+    mut entered: EventReader<TextEntered>,
+    mut editor: ResMut<PatternEditor>,
+    mut patterns: ResMut<Patterns>,
+    mut time_signature: ResMut<TimeSignature>,
+    mut polyrhythm: ResMut<Polyrhythm>,
+    mut text_entry: ResMut<TextEntry>,
+    audio_handles: Res<AudioHandles>,
+) {
+    for (interaction, EditorCell { sound, step }) in &cells {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        // Only the clicked note changes, notes off the grid stay where they are
+        let mut pattern = patterns.selected().cloned().unwrap_or_else(|| Pattern {
+            name: patterns.new_name(),
+            notes: Vec::new(),
+        });
+        pattern.toggle(
+            *step as f64 / editor.steps_per_beat as f64,
+            *sound,
+            audio_handles.tick,
+        );
+        patterns.update(pattern, &mut time_signature);
+        if polyrhythm.divisions().is_some() {
+            polyrhythm.stop();
+        }
+    }
+
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match button {
+            EditorButton::Previous => {
+                patterns.previous(&mut time_signature);
+                editor.fit(&patterns);
+            }
+            EditorButton::Next => {
+                patterns.next(&mut time_signature);
+                editor.fit(&patterns);
+            }
+            EditorButton::New => {
+                let pattern = Pattern {
+                    name: patterns.new_name(),
+                    notes: Vec::new(),
+                };
+                patterns.select(None, &mut time_signature);
+                patterns.update(pattern, &mut time_signature);
+            }
+            EditorButton::Rename => {
+                let name = patterns
+                    .selected()
+                    .map_or_else(String::new, |p| p.name.clone());
+                text_entry.start(TextTarget::PatternName, name);
+            }
+            EditorButton::Save => patterns.save_selected(),
+            EditorButton::StepsDown => editor.change_steps(false),
+            EditorButton::StepsUp => editor.change_steps(true),
+        }
+        // A pattern replaces the grids of a polyrhythm
+        if patterns.selected().is_some() && polyrhythm.divisions().is_some() {
+            polyrhythm.stop();
+        }
+    }
+
+    for TextEntered(target, name) in entered.read() {
+        let name = name.trim();
+        if *target != TextTarget::PatternName || name.is_empty() {
+            continue;
+        }
+        // A name another pattern has is refused, and left to be edited
+        if patterns
+            .selected()
+            .is_some_and(|pattern| pattern.name != name)
+            && !patterns.rename_selected(name)
+        {
+            text_entry.start(TextTarget::PatternName, name.to_string());
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use bevy::prelude::*;
+
+use crate::input_time::Hand;
+
+/// Ratios cycled through by the polyrhythm button, as (left, right) onsets per pulse.
+pub const RATIOS: &[(u32, u32)] = &[(3, 2), (4, 3), (5, 4), (5, 3), (7, 4)];
+
+/// Two grids running at once, one per hand, each split evenly over the pulse.
+///
+/// While active it replaces [`crate::Division`] for judging taps.
+#[derive(Resource, Default)]
+pub struct Polyrhythm {
+    ratio: Option<usize>,
+}
+
+impl Polyrhythm {
+    /// Onsets per pulse of each hand, indexed by [`Hand::index`].
+    pub fn divisions(&self) -> Option<[u32; 2]> {
+        self.ratio.map(|i| [RATIOS[i].0, RATIOS[i].1])
+    }
+
+    pub fn division(&self, hand: Hand) -> Option<u32> {
+        self.divisions().map(|divisions| divisions[hand.index()])
+    }
+
+    pub fn stop(&mut self) {
+        self.ratio = None;
+    }
+
+    /// Switches to the next of [`RATIOS`], turning off after the last one.
+    pub fn next(&mut self) {
+        self.ratio = match self.ratio {
+            None => Some(0),
+            Some(i) if i + 1 < RATIOS.len() => Some(i + 1),
+            Some(_) => None,
+        };
+    }
+
+    pub fn label(&self) -> String {
+        match self.divisions() {
+            Some([left, right]) => format!("{}:{}", left, right),
+            None => "off".to_string(),
+        }
+    }
+}
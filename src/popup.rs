@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use bevy::utils::{Duration, Instant};
+
+use crate::{
+    CIRCLE_SIZE, Clock, Tapped,
+    judgement::{Judgement, Tier},
+};
+
+const POPUP_DURATION: Duration = Duration::from_millis(600);
+/// Height a popup rises by while it fades.
+const POPUP_RISE: f32 = 48.0;
+const POPUP_Y: f32 = CIRCLE_SIZE * 0.3;
+
+/// Judged taps in a row, broken by a miss.
+#[derive(Resource, Default)]
+pub struct Combo {
+    pub count: u32,
+    pub best: u32,
+}
+
+impl Combo {
+    pub fn judge(&mut self, tier: Tier) {
+        if tier == Tier::Miss {
+            self.count = 0;
+        } else {
+            self.count += 1;
+            self.best = self.best.max(self.count);
+        }
+    }
+}
+
+/// Judgement of the latest tap, shown on the clock.
+#[derive(Component)]
+pub struct Popup {
+    spawned: Instant,
+}
+
+#[derive(Component)]
+pub struct ComboText;
+
+fn tier_color(tier: Tier) -> Color {
+    match tier {
+        Tier::Perfect => Color::srgb(1.0, 0.85, 0.3),
+        Tier::Great => Color::srgb(0.4, 0.9, 0.4),
+        Tier::Good => Color::srgb(0.4, 0.7, 1.0),
+        Tier::Bad => Color::srgb(0.9, 0.5, 0.2),
+        Tier::Miss => Color::srgb(0.9, 0.2, 0.2),
+    }
+}
+
+pub fn judgement_popups(
+    mut commands: Commands,
+    mut tapped: EventReader<Tapped>,
+    judgement: Res<Judgement>,
+    mut combo: ResMut<Combo>,
+    popups: Query<Entity, With<Popup>>,
+    clock: Query<Entity, With<Clock>>,
+) {
+    let mut latest = None;
+    for Tapped(delta) in tapped.read() {
+        let tier = judgement.tier(delta.delta);
+        combo.judge(tier);
+        latest = Some((tier, delta.delta));
+    }
+    // Only the latest tap is shown
+    let Some((tier, delta)) = latest else {
+        return;
+    };
+
+    for popup in &popups {
+        commands.entity(popup).despawn_recursive();
+    }
+
+    // Early taps are fast, anything but a perfect says which way it was off
+    let label = match tier {
+        Tier::Perfect => tier.label().to_string(),
+        _ if delta < 0.0 => format!("{}\nFAST", tier.label()),
+        _ => format!("{}\nSLOW", tier.label()),
+    };
+
+    for clock in &clock {
+        commands.entity(clock).with_child((
+            Popup {
+                spawned: Instant::now(),
+            },
+            Text2d::new(label.clone()),
+            TextFont {
+                font_size: 48.0,
+                ..default()
+            },
+            TextLayout::new_with_justify(JustifyText::Center),
+            TextColor(tier_color(tier)),
+            Transform::from_xyz(0.0, POPUP_Y, 10.0),
+        ));
+    }
+}
+
+// Rises and fades out, then is removed
+pub fn animate_popups(
+    mut commands: Commands,
+    mut popups: Query<(Entity, &Popup, &mut Transform, &mut TextColor)>,
+) {
+    let now = Instant::now();
+    for (entity, popup, mut transform, mut color) in &mut popups {
+        let t = now.saturating_duration_since(popup.spawned).as_secs_f32()
+            / POPUP_DURATION.as_secs_f32();
+        if t >= 1.0 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        transform.translation.y = POPUP_Y + POPUP_RISE * t;
+        color.0.set_alpha(1.0 - t * t);
+    }
+}
+
+pub fn set_combo_text(combo: Res<Combo>, mut query: Query<&mut Text2d, With<ComboText>>) {
+    if !combo.is_changed() {
+        return;
+    }
+
+    for mut text in &mut query {
+        text.0 = if combo.count > 1 {
+            format!("{} combo", combo.count)
+        } else {
+            String::new()
+        };
+    }
+}
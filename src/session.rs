@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+use bevy::utils::Instant;
+
+use crate::{
+    Delta, Missed, Tapped,
+    judgement::{Judgement, Tier},
+    miss::Untapped,
+};
+
+const MAX_SCORE: f64 = 1_000_000.0;
+/// Share of the score given by the longest combo, the rest is from the tiers.
+const COMBO_SHARE: f64 = 0.1;
+/// Lowest fraction of [`MAX_SCORE`] for each grade, from best to worst.
+const GRADES: &[(f64, &str)] = &[(0.95, "S"), (0.9, "A"), (0.8, "B"), (0.7, "C"), (0.6, "D")];
+
+fn tier_points(tier: Tier) -> f64 {
+    match tier {
+        Tier::Perfect => 1.0,
+        Tier::Great => 0.8,
+        Tier::Good => 0.5,
+        Tier::Bad => 0.2,
+        Tier::Miss => 0.0,
+    }
+}
+
+// A judged tap
+struct Record {
+    position: f64,
+    tier: Tier,
+}
+
+/// Score of a session.
+#[derive(Clone, Copy)]
+pub struct Score {
+    pub points: u32,
+    pub grade: &'static str,
+    pub records: usize,
+    pub misses: usize,
+    pub max_combo: u32,
+}
+
+/// Judgements between the start and the end of a practice session, scored
+/// when it ends.
+#[derive(Resource, Default)]
+pub struct Session {
+    running: bool,
+    started: Option<Instant>,
+    records: Vec<Record>,
+    // Missed onsets, each scored as a miss until a late tap replaces it
+    untapped: Untapped,
+    // The score of the last finished session
+    result: Option<Score>,
+}
+
+impl Session {
+    /// Starts a new session, or ends the running one and scores it.
+    pub fn toggle(&mut self) {
+        if self.running {
+            self.result = Some(self.score());
+        } else {
+            self.records.clear();
+            self.untapped = Untapped::default();
+            self.result = None;
+            self.started = Some(Instant::now());
+        }
+        self.running = !self.running;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    fn miss(&mut self, delta: &Delta) {
+        self.untapped.push(delta);
+    }
+
+    fn tap(&mut self, delta: &Delta, tier: Tier) {
+        // A tap too late for its window replaces the miss already recorded
+        self.untapped.replace(delta);
+        self.records.push(Record {
+            position: delta.position,
+            tier,
+        });
+    }
+
+    fn score(&self) -> Score {
+        let mut tiers = self
+            .records
+            .iter()
+            .map(|record| (record.position, record.tier))
+            .chain(
+                self.untapped
+                    .positions()
+                    .map(|position| (position, Tier::Miss)),
+            )
+            .collect::<Vec<_>>();
+        tiers.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut combo = 0;
+        let mut max_combo = 0;
+        for (_, tier) in &tiers {
+            combo = if *tier == Tier::Miss { 0 } else { combo + 1 };
+            max_combo = max_combo.max(combo);
+        }
+
+        let fraction = if tiers.is_empty() {
+            0.0
+        } else {
+            let accuracy = tiers
+                .iter()
+                .map(|(_, tier)| tier_points(*tier))
+                .sum::<f64>()
+                / tiers.len() as f64;
+            (1.0 - COMBO_SHARE) * accuracy + COMBO_SHARE * max_combo as f64 / tiers.len() as f64
+        };
+        let grade = GRADES
+            .iter()
+            .find(|(min, _)| fraction >= *min)
+            .map_or("F", |(_, grade)| grade);
+
+        Score {
+            points: (fraction * MAX_SCORE).round() as u32,
+            grade,
+            records: tiers.len(),
+            misses: tiers.iter().filter(|(_, tier)| *tier == Tier::Miss).count(),
+            max_combo,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        let label = |score: Score| {
+            format!(
+                "{} {} ({} notes, {} misses, max combo {})",
+                score.points, score.grade, score.records, score.misses, score.max_combo
+            )
+        };
+        if self.running {
+            format!("running {}", label(self.score()))
+        } else {
+            self.result.map_or("-".to_string(), label)
+        }
+    }
+}
+
+pub fn session(
+    mut session: ResMut<Session>,
+    mut tapped: EventReader<Tapped>,
+    mut missed: EventReader<Missed>,
+    judgement: Res<Judgement>,
+) {
+    if !session.running {
+        tapped.clear();
+        missed.clear();
+        return;
+    }
+
+    // The key that started the session is also a tap, ignore it and anything before
+    let started = session.started;
+    let in_session = |time: Instant| started.is_none_or(|started| time > started);
+
+    for Missed(delta) in missed.read().filter(|Missed(delta)| in_session(delta.time)) {
+        session.miss(delta);
+    }
+    for Tapped(delta) in tapped.read().filter(|Tapped(delta)| in_session(delta.time)) {
+        session.tap(delta, delta.tier(&judgement));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_weighs_tiers_and_the_longest_combo() {
+        let mut session = Session::default();
+        let score = session.score();
+        assert_eq!((score.points, score.grade), (0, "F"));
+
+        for position in 0..3 {
+            session.tap(&Delta::tap(None, position as f64, 0.0), Tier::Perfect);
+        }
+        session.miss(&Delta::miss(None, 3.0));
+        session.tap(&Delta::tap(None, 4.0, 0.02), Tier::Great);
+        let score = session.score();
+        assert_eq!((score.points, score.grade), (744_000, "C"));
+        assert_eq!((score.records, score.misses, score.max_combo), (5, 1, 3));
+
+        // A tap too late for its window takes the place of the miss
+        session.tap(&Delta::tap(None, 3.0, 0.04), Tier::Bad);
+        let score = session.score();
+        assert_eq!((score.points, score.grade), (820_000, "B"));
+        assert_eq!((score.records, score.misses, score.max_combo), (5, 0, 5));
+    }
+}
@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+use bevy::utils::Instant;
+
+use crate::{Bpm, Tapped, TimeSignature, beat_grid::BeatGrid};
+
+/// When the speed trainer judges the player and changes the tempo.
+#[derive(Clone, Copy)]
+pub enum RampInterval {
+    Bars(u32),
+    Taps(u32),
+}
+
+/// Accuracy of the taps since the tempo last changed.
+#[derive(Default)]
+pub struct StepAccuracy {
+    // Taps pressed before the step started are ignored
+    started: Option<Instant>,
+    start_beat: Option<i64>,
+    pub taps: u32,
+    error_sum: f64,
+}
+
+impl StepAccuracy {
+    pub fn reset(&mut self) {
+        *self = Self {
+            started: Some(Instant::now()),
+            ..default()
+        };
+    }
+
+    /// Counts new taps and returns the number of beats since the step started at or before `beat`.
+    pub fn update(&mut self, beat: i64, tapped: &mut EventReader<Tapped>) -> i64 {
+        let start_beat = *self.start_beat.get_or_insert(beat);
+        // The key that started the trainer is also a tap
+        let started = self.started;
+        for Tapped(delta) in tapped
+            .read()
+            .filter(|Tapped(delta)| started.is_none_or(|started| delta.time > started))
+        {
+            self.taps += 1;
+            self.error_sum += delta.delta.abs();
+        }
+        beat - start_beat
+    }
+
+    /// Mean |delta| in seconds, infinite without taps.
+    pub fn mean(&self) -> f64 {
+        if self.taps > 0 {
+            self.error_sum / self.taps as f64
+        } else {
+            f64::INFINITY
+        }
+    }
+}
+
+/// Raises the tempo while the player keeps up, and drops it back when they don't.
+#[derive(Resource)]
+pub struct SpeedTrainer {
+    pub enabled: bool,
+    /// BPM added on success and removed on failure.
+    pub step: f32,
+    pub interval: RampInterval,
+    /// Largest mean |delta| in seconds that still counts as keeping up.
+    pub threshold: f64,
+    accuracy: StepAccuracy,
+}
+
+impl Default for SpeedTrainer {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            step: 2.0,
+            interval: RampInterval::Bars(4),
+            threshold: 1.5 / 60.0,
+            accuracy: StepAccuracy::default(),
+        }
+    }
+}
+
+impl SpeedTrainer {
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.accuracy.reset();
+    }
+
+    /// Switches between judging every few bars and every few taps.
+    pub fn toggle_interval(&mut self) {
+        self.interval = match self.interval {
+            RampInterval::Bars(_) => RampInterval::Taps(16),
+            RampInterval::Taps(_) => RampInterval::Bars(4),
+        };
+        self.accuracy.reset();
+    }
+
+    pub fn label(&self) -> String {
+        if !self.enabled {
+            return "off".to_string();
+        }
+
+        let interval = match self.interval {
+            RampInterval::Bars(bars) => format!("{} bars", bars),
+            RampInterval::Taps(taps) => format!("{} taps", taps),
+        };
+        format!(
+            "{:+} / {}, {:.1}ms",
+            self.step,
+            interval,
+            self.threshold * 1000.0
+        )
+    }
+}
+
+pub fn speed_trainer(
+    mut speed_trainer: ResMut<SpeedTrainer>,
+    mut tapped: EventReader<Tapped>,
+    mut bpm: ResMut<Bpm>,
+    beat_grid: Res<BeatGrid>,
+    time_signature: Res<TimeSignature>,
+) {
+    if !speed_trainer.enabled {
+        tapped.clear();
+        return;
+    }
+
+    let beat = beat_grid.beat_before(Instant::now());
+    let trainer = speed_trainer.as_mut();
+    let beats = trainer.accuracy.update(beat, &mut tapped);
+
+    let done = match trainer.interval {
+        RampInterval::Bars(bars) => beats >= (bars * time_signature.beats_per_bar()) as i64,
+        RampInterval::Taps(taps) => trainer.accuracy.taps >= taps,
+    };
+    if !done {
+        return;
+    }
+
+    // An idle player isn't judged, the tempo stays until they tap again
+    if trainer.accuracy.taps == 0 {
+        trainer.accuracy.reset();
+        return;
+    }
+
+    let step = if trainer.accuracy.mean() <= trainer.threshold {
+        trainer.step
+    } else {
+        -trainer.step
+    };
+    bpm.step(step);
+
+    trainer.accuracy.reset();
+}
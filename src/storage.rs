@@ -0,0 +1,55 @@
+use bevy::prelude::*;
+
+/// The value saved under `key`, if any. Values are kept in a file on native and
+/// in `localStorage` on the web.
+pub fn load(key: &str) -> Option<String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::fs::read_to_string(path(key)).ok()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        local_storage()?.get_item(key).ok()?
+    }
+}
+
+/// Saves `value` under `key`, replacing the previous value.
+pub fn save(key: &str, value: &str) {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let path = path(key);
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, value));
+        if let Err(err) = result {
+            warn!("Failed to save {}: {err}", path.display());
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let saved = local_storage().is_some_and(|storage| storage.set_item(key, value).is_ok());
+        if !saved {
+            warn!("Failed to save {key}");
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path(key: &str) -> std::path::PathBuf {
+    use std::{env::var_os, path::PathBuf};
+
+    let data = var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_default();
+    data.join("tempo-trainer").join(format!("{key}.txt"))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
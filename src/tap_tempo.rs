@@ -0,0 +1,78 @@
+use bevy::prelude::*;
+use bevy::utils::{Duration, Instant};
+
+use crate::stats::median;
+
+/// Presses the estimate is made from.
+const MAX_PRESSES: usize = 8;
+const MIN_PRESSES: usize = 4;
+/// A longer pause starts a new estimate.
+const RESET_GAP: Duration = Duration::from_secs(2);
+/// Intervals further than this fraction from the median are left out.
+const MAX_DEVIATION: f64 = 0.25;
+
+/// Sets the tempo from the intervals between the player's presses.
+#[derive(Resource, Default)]
+pub struct TapTempo {
+    pub enabled: bool,
+    presses: Vec<Instant>,
+    estimate: Option<f64>,
+}
+
+impl TapTempo {
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.presses.clear();
+        self.estimate = None;
+    }
+
+    /// Adds a press, returns the new estimate in BPM once there are enough presses.
+    pub fn press(&mut self, press: Instant) -> Option<f64> {
+        if self
+            .presses
+            .last()
+            .is_some_and(|last| press.saturating_duration_since(*last) > RESET_GAP)
+        {
+            self.presses.clear();
+        }
+        self.presses.push(press);
+        if self.presses.len() > MAX_PRESSES {
+            self.presses.remove(0);
+        }
+
+        if self.presses.len() < MIN_PRESSES {
+            return None;
+        }
+        self.estimate = estimate(&self.presses);
+        self.estimate
+    }
+
+    pub fn label(&self) -> String {
+        match (self.enabled, self.estimate) {
+            (false, _) => "off".to_string(),
+            (true, Some(bpm)) => format!("{:.1} BPM", bpm),
+            (true, None) => format!(
+                "tap {} times",
+                MIN_PRESSES - self.presses.len().min(MIN_PRESSES)
+            ),
+        }
+    }
+}
+
+/// BPM from the mean interval, without the intervals far from the median.
+fn estimate(presses: &[Instant]) -> Option<f64> {
+    let intervals = presses
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).as_secs_f64())
+        .collect::<Vec<_>>();
+    let median = median(&mut intervals.clone())?;
+
+    let kept = intervals
+        .into_iter()
+        .filter(|interval| (interval - median).abs() <= MAX_DEVIATION * median)
+        .collect::<Vec<_>>();
+    if kept.is_empty() || median <= 0.0 {
+        return None;
+    }
+    Some(60.0 * kept.len() as f64 / kept.iter().sum::<f64>())
+}
@@ -0,0 +1,85 @@
+use bevy::input::{
+    ButtonState,
+    keyboard::{Key, KeyboardInput},
+};
+use bevy::prelude::*;
+
+/// What the typed text is for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TextTarget {
+    PatternName,
+    DropoutSeed,
+    Bpm,
+    JudgementWindows,
+}
+
+/// Text being typed. While active, keys don't tap or control the trainer.
+#[derive(Resource, Default)]
+pub struct TextEntry {
+    target: Option<TextTarget>,
+    pub text: String,
+}
+
+impl TextEntry {
+    pub fn start(&mut self, target: TextTarget, text: String) {
+        self.target = Some(target);
+        self.text = text;
+    }
+
+    pub fn target(&self) -> Option<TextTarget> {
+        self.target
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.target.is_some()
+    }
+}
+
+/// Sent when the entry is confirmed with enter.
+#[derive(Event)]
+pub struct TextEntered(pub TextTarget, pub String);
+
+pub fn text_entry(
+    mut text_entry: ResMut<TextEntry>,
+    mut keyboard_input: EventReader<KeyboardInput>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut entered: EventWriter<TextEntered>,
+    mut was_active: Local<bool>,
+) {
+    // Skip the frame the entry started, its keys may have started it
+    let started = !*was_active;
+    *was_active = text_entry.is_active();
+    if !text_entry.is_active() || started {
+        keyboard_input.clear();
+        return;
+    }
+
+    for input in keyboard_input.read() {
+        if input.state != ButtonState::Pressed {
+            continue;
+        }
+        match &input.logical_key {
+            Key::Enter => {
+                if let Some(target) = text_entry.target.take() {
+                    entered.send(TextEntered(target, std::mem::take(&mut text_entry.text)));
+                }
+                // Enter also starts typing the BPM, the press only confirms
+                keys.clear_just_pressed(KeyCode::Enter);
+            }
+            Key::Escape => {
+                text_entry.target = None;
+                text_entry.text.clear();
+                // Escape also switches every key to tapping, the press only cancels
+                keys.clear_just_pressed(KeyCode::Escape);
+            }
+            Key::Backspace => {
+                text_entry.text.pop();
+            }
+            Key::Space => text_entry.text.push(' '),
+            Key::Character(characters) => text_entry
+                .text
+                .extend(characters.chars().filter(|c| !c.is_control())),
+            _ => {}
+        }
+    }
+}
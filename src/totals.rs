@@ -0,0 +1,157 @@
+use bevy::prelude::*;
+use bevy::utils::Instant;
+
+use crate::{
+    Delta, Missed, Tapped,
+    judgement::{Judgement, Tier},
+    miss::Untapped,
+};
+
+/// Running counts of the judged taps and missed onsets since the statistics were
+/// last reset, where the bar chart only keeps the latest ones.
+#[derive(Resource, Default)]
+pub struct Totals {
+    pub fast: u32,
+    pub slow: u32,
+    // Indexed like `Tier::ALL`
+    tiers: [u32; Tier::ALL.len()],
+    /// Onsets missed without a tap, also counted as [`Tier::Miss`].
+    pub untapped: u32,
+    missed: Untapped,
+    // Sum of the signed deltas of the taps
+    delta_sum: f64,
+    taps: u32,
+    // Taps up to the reset are still in flight and ignored
+    since: Option<Instant>,
+}
+
+impl Totals {
+    pub fn reset(&mut self) {
+        *self = Self {
+            since: Some(Instant::now()),
+            ..default()
+        };
+    }
+
+    pub fn count(&self, tier: Tier) -> u32 {
+        Tier::ALL
+            .iter()
+            .position(|t| *t == tier)
+            .map_or(0, |i| self.tiers[i])
+    }
+
+    fn add(&mut self, tier: Tier, count: i32) {
+        if let Some(i) = Tier::ALL.iter().position(|t| *t == tier) {
+            self.tiers[i] = self.tiers[i].saturating_add_signed(count);
+        }
+    }
+
+    fn miss(&mut self, delta: &Delta) {
+        self.add(Tier::Miss, 1);
+        self.untapped += 1;
+        self.missed.push(delta);
+    }
+
+    fn tap(&mut self, delta: &Delta, tier: Tier) {
+        // A tap too late for its window replaces the miss already counted
+        if self.missed.replace(delta) {
+            self.add(Tier::Miss, -1);
+            self.untapped -= 1;
+        }
+        self.missed.forget_before(delta);
+
+        self.add(tier, 1);
+        // Counted like the popups, a perfect is neither fast nor slow
+        match tier {
+            Tier::Perfect => {}
+            _ if delta.delta < 0.0 => self.fast += 1,
+            _ => self.slow += 1,
+        }
+        self.delta_sum += delta.delta;
+        self.taps += 1;
+    }
+
+    /// Signed mean delta of the taps, negative when rushing.
+    pub fn bias(&self) -> Option<f64> {
+        (self.taps > 0).then(|| self.delta_sum / self.taps as f64)
+    }
+}
+
+pub fn count_totals(
+    mut totals: ResMut<Totals>,
+    mut tapped: EventReader<Tapped>,
+    mut missed: EventReader<Missed>,
+    judgement: Res<Judgement>,
+) {
+    let since = totals.since;
+    let counted = |time: Instant| since.is_none_or(|since| time > since);
+
+    // Read first, a late tap can only replace a miss sent before it
+    for Missed(delta) in missed.read().filter(|Missed(delta)| counted(delta.time)) {
+        totals.miss(delta);
+    }
+    for Tapped(delta) in tapped.read().filter(|Tapped(delta)| counted(delta.time)) {
+        totals.tap(delta, delta.tier(&judgement));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::input_time::Hand;
+
+    #[test]
+    fn late_tap_takes_the_place_of_its_miss() {
+        let mut totals = Totals::default();
+        totals.tap(&Delta::tap(None, 0.0, -0.01), Tier::Perfect);
+        totals.tap(&Delta::tap(None, 1.0, -0.02), Tier::Great);
+        totals.miss(&Delta::miss(None, 2.0));
+        totals.miss(&Delta::miss(None, 3.0));
+        assert_eq!((totals.count(Tier::Miss), totals.untapped), (2, 2));
+
+        totals.tap(&Delta::tap(None, 2.0, 0.04), Tier::Bad);
+        assert_eq!((totals.count(Tier::Miss), totals.untapped), (1, 1));
+        assert_eq!(
+            (totals.count(Tier::Bad), totals.fast, totals.slow),
+            (1, 1, 1)
+        );
+        assert!((totals.bias().unwrap() - 0.01 / 3.0).abs() < 1e-9);
+
+        // A tap a beat past a miss is for a later onset
+        totals.tap(&Delta::tap(None, 4.0, 0.0), Tier::Perfect);
+        totals.tap(&Delta::tap(None, 3.0, 0.0), Tier::Perfect);
+        assert_eq!((totals.count(Tier::Miss), totals.untapped), (1, 1));
+    }
+
+    #[test]
+    fn miss_is_only_replaced_on_its_own_grid() {
+        let mut totals = Totals::default();
+        totals.miss(&Delta::miss(Some(Hand::Left), 1.0));
+        totals.tap(&Delta::tap(Some(Hand::Right), 1.0, 0.0), Tier::Perfect);
+        assert_eq!(totals.untapped, 1);
+        totals.tap(&Delta::tap(Some(Hand::Left), 1.0, 0.0), Tier::Perfect);
+        assert_eq!((totals.count(Tier::Miss), totals.untapped), (0, 0));
+        assert_eq!(totals.count(Tier::Perfect), 2);
+    }
+
+    #[test]
+    fn taps_before_the_reset_are_not_counted() {
+        let mut world = World::new();
+        world.init_resource::<Totals>();
+        world.insert_resource(Judgement::default());
+        world.init_resource::<Events<Tapped>>();
+        world.init_resource::<Events<Missed>>();
+        world.send_event(Tapped(Delta::tap(None, 0.0, 0.0)));
+        world.resource_mut::<Totals>().reset();
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        world.send_event(Tapped(Delta::tap(None, 1.0, 0.04)));
+        world.send_event(Missed(Delta::miss(None, 2.0)));
+
+        world.run_system_once(count_totals).unwrap();
+        let totals = world.resource::<Totals>();
+        assert_eq!(totals.count(Tier::Perfect), 0);
+        assert_eq!((totals.count(Tier::Bad), totals.count(Tier::Miss)), (1, 1));
+    }
+}